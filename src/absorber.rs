@@ -0,0 +1,105 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use serde::Deserialize;
+
+use crate::{collision::{Collider, is_colliding, is_circle_in_rect}, neutron::Neutron};
+
+const ABSORBER_COLOR: Color = Color::rgb(0.35, 0.35, 0.4);
+const CONTROL_ROD_COLOR: Color = Color::rgb(0.55, 0.65, 0.75);
+pub const CONTROL_ROD_SIZE: Vec2 = Vec2::new(20., 200.);
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum AbsorberShape {
+    Circle { radius: f32 },
+    Rectangle { size: Vec2 }
+}
+
+/// Swallows any neutron that touches it.
+#[derive(Component, Debug, Clone)]
+pub struct Absorber {
+    pub shape: AbsorberShape
+}
+
+/// An absorber placed by the player during setup, as opposed to one that is part of the level.
+#[derive(Component)]
+pub struct ControlRod;
+
+impl Absorber {
+    pub fn is_touching(&self, absorber_pos: Vec2, other: (&Vec2, &Collider)) -> bool {
+        match self.shape {
+            AbsorberShape::Circle { radius } => is_colliding((&absorber_pos, &Collider::new(radius)), other),
+            AbsorberShape::Rectangle { size } => is_circle_in_rect(other, absorber_pos, size / 2.)
+        }
+    }
+}
+
+pub fn spawn_absorber(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    position: Vec2,
+    shape: AbsorberShape
+) {
+    commands.spawn(absorber_bundle(meshes, materials, position, shape, ABSORBER_COLOR));
+}
+
+pub fn spawn_control_rod(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    position: Vec2
+) {
+    commands.spawn((
+        absorber_bundle(meshes, materials, position, AbsorberShape::Rectangle { size: CONTROL_ROD_SIZE }, CONTROL_ROD_COLOR),
+        ControlRod
+    ));
+}
+
+fn absorber_bundle(
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    position: Vec2,
+    shape: AbsorberShape,
+    color: Color
+) -> (MaterialMesh2dBundle<ColorMaterial>, Absorber) {
+    let mesh = match shape {
+        AbsorberShape::Circle { radius } => meshes.add(shape::Circle::new(radius).into()),
+        AbsorberShape::Rectangle { size } => meshes.add(shape::Quad { size, flip: false }.into())
+    };
+
+    (
+        MaterialMesh2dBundle {
+            mesh: mesh.into(),
+            material: materials.add(ColorMaterial::from(color)),
+            transform: Transform { translation: Vec3::from((position, 1.5)), ..default() },
+            ..default()
+        },
+        Absorber { shape }
+    )
+}
+
+#[allow(clippy::type_complexity)]
+pub fn absorber_collision(
+    par_commands: ParallelCommands,
+    absorbers: Query<(&Transform, &Absorber), Without<Neutron>>,
+    neutrons: Query<(Entity, &Transform, &Collider), (With<Neutron>, Without<Absorber>)>
+) {
+    neutrons.par_iter().for_each(|(neutron_entity, neutron_transform, neutron_collider)| {
+        let neutron_pos = neutron_transform.translation.xy();
+
+        for (absorber_transform, absorber) in &absorbers {
+            if !absorber.is_touching(absorber_transform.translation.xy(), (&neutron_pos, neutron_collider)) {
+                continue;
+            }
+
+            par_commands.command_scope(|mut commands| commands.entity(neutron_entity).despawn_recursive());
+            return;
+        }
+    });
+}
+
+pub fn absorber_cleanup(
+    mut commands: Commands,
+    absorber_q: Query<Entity, With<Absorber>>
+) {
+    absorber_q.for_each(|absorber_entity| commands.entity(absorber_entity).despawn_recursive());
+}
@@ -1,33 +1,54 @@
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
-use crate::{collision::{Collider, is_colliding, CollisionEvent}, neutron::*};
+use crate::{collision::{Collider, is_colliding, CollisionEvent}, fragment::DelayedEmission, neutron::*, objective::AtomTag, ENERGY_RELEASED, fragment::{fragment_cleanup, fragment_decay}, level_manager::LevelSetupSet, GameState};
 
-const ATOM_SIZE: f32 = 30.;
+pub const ATOM_SIZE: f32 = 30.;
 
 #[derive(Component, Debug, Clone)]
 pub struct Atom {
-    pub num_neutrons: i32
+    pub num_neutrons: i32,
+    pub split_pattern: SplitPattern,
+    pub delayed_emission: Option<DelayedEmission>
 }
 
+/// Atoms splitting when hit, and the fragments some of them leave behind.
+pub struct AtomPlugin;
+
+impl Plugin for AtomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::SETUP), (atom_cleanup, fragment_cleanup).in_set(LevelSetupSet::Cleanup))
+            .add_systems(Update, (atom_collision, fragment_decay).run_if(in_state(GameState::GAME)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_atom(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     meshes: &mut ResMut<Assets<Mesh>>, 
     materials: &mut ResMut<Assets<ColorMaterial>>,
     position: Vec2,
-    num_neutron: i32
+    num_neutron: i32,
+    split_pattern: SplitPattern,
+    delayed_emission: Option<DelayedEmission>,
+    tag: Option<AtomTag>
 ) {
-    commands.spawn((
+    let mut atom = commands.spawn((
         MaterialMesh2dBundle {
             mesh: meshes.add(shape::Circle::new(ATOM_SIZE).into()).into(),
-            material: materials.add(ColorMaterial::from(Color::YELLOW)),
+            material: materials.add(ColorMaterial::from(atom_color(tag))),
             transform: Transform { translation: Vec3::from((position, 2.)), ..default() },
             ..default()
         },
-        Atom { num_neutrons: num_neutron },
+        Atom { num_neutrons: num_neutron, split_pattern, delayed_emission },
         Collider { radius: ATOM_SIZE * 0.75 }
-    ))
-    .with_children(|parent| {
+    ));
+
+    if let Some(tag) = tag {
+        atom.insert(tag);
+    }
+
+    atom.with_children(|parent| {
         parent.spawn(Text2dBundle {
             text: Text::from_section(
                 format!("{num_neutron}"), 
@@ -42,6 +63,14 @@ pub fn spawn_atom(
     });
 }
 
+pub fn atom_color(tag: Option<AtomTag>) -> Color {
+    match tag {
+        Some(AtomTag::Target) => Color::GREEN,
+        Some(AtomTag::Protected) => Color::RED,
+        None => Color::YELLOW
+    }
+}
+
 pub fn atom_collision(
     par_commands: ParallelCommands,
     atoms: Query<(Entity, &Transform, &Atom, &Collider), Without<Neutron>>,
@@ -58,16 +87,23 @@ pub fn atom_collision(
 
             par_commands.command_scope(| mut commands | {
                 commands.entity(atom_entity).despawn_recursive();
-                commands.entity(neutron_entity).despawn();
+                commands.entity(neutron_entity).despawn_recursive();
 
                 let neutron_velocity = neutron.velocity;
+                let neutron_generation = neutron.generation;
                 let num_neutrons = atom.num_neutrons;
+                let split_pattern = atom.split_pattern.clone();
+                let delayed_emission = atom.delayed_emission;
 
                 commands.add(move |world: &mut World| {
                     world.send_event(CollisionEvent {
-                        neutron_velocity: neutron_velocity, 
+                        neutron_velocity,
+                        neutron_generation, 
                         atom_position: atom_pos,
-                        num_neutrons: num_neutrons
+                        num_neutrons,
+                        energy: ENERGY_RELEASED,
+                        split_pattern,
+                        delayed_emission
                     });
                 });
             });
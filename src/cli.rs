@@ -0,0 +1,218 @@
+use std::{fs, process};
+
+use bevy::{log::Level as LogLevel, prelude::*, window::WindowResolution};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{absorber::spawn_control_rod, atom::Atom, collision::{Collider, is_colliding}, level_manager::{get_level, Level, LevelFile, LevelSetupSet, LevelStats}, neutron::{NEUTRON_SIZE, spawn_neutron}, player_controls::{MIN_LAUNCH_SPEED, MAX_LAUNCH_SPEED}, util::clamp_vec2_by_length, settings::{Settings, SettingsWindowMode}, telemetry::{TelemetryFormat, TelemetryOptions}, GameState, GameStats, MIN_SIMULATION_SPEED, MAX_SIMULATION_SPEED};
+
+const USAGE: &str = "\
+Usage: nucleus [options]
+
+  --level N                start at level N instead of the menu
+  --level-file PATH        play the level in a RON file instead of a built in one
+  --placements PATH        place the neutrons and control rods in a RON file and start the run
+  --speed X                simulation speed to start the level at
+  --log-level LEVEL        error, warn, info, debug or trace (default debug)
+  --windowed WxH           open in a window of the given size
+  --telemetry [csv|jsonl]  log every fission to a file under telemetry/
+  --help                   show this message";
+
+/// Options given on the command line, mostly for getting straight into a level while working on it.
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+    pub level: Option<i32>,
+    pub level_file: Option<Level>,
+    pub placements: Option<Placements>,
+    pub speed: Option<f32>,
+    pub log_level: LogLevel,
+    pub window_size: Option<Vec2>,
+    pub telemetry: Option<TelemetryFormat>
+}
+
+/// Neutrons and control rods to place as soon as the level is set up, before starting the run.
+#[derive(Resource, Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Placements {
+    pub neutrons: Vec<PlacedNeutron>,
+    pub control_rods: Vec<Vec2>
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PlacedNeutron {
+    pub position: Vec2,
+    pub velocity: Vec2
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        LaunchOptions { level: None, level_file: None, placements: None, speed: None, log_level: LogLevel::DEBUG, window_size: None, telemetry: None }
+    }
+}
+
+impl LaunchOptions {
+    /// Parses the arguments after the program name, printing the usage and exiting if they are wrong.
+    pub fn from_env() -> Self {
+        LaunchOptions::from_args(std::env::args().skip(1)).unwrap_or_else(|error| {
+            eprintln!("{error}\n\n{USAGE}");
+            process::exit(2);
+        })
+    }
+
+    /// Takes both `--flag value` and `--flag=value`.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut launch_options = LaunchOptions::default();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None)
+            };
+
+            // the format is optional, so only a value that isn't another flag is taken
+            if flag == "--telemetry" {
+                let format = inline_value.or_else(|| args.next_if(|next| !next.starts_with("--")));
+
+                launch_options.telemetry = Some(match format {
+                    Some(format) => parse(&flag, &format)?,
+                    None => TelemetryFormat::Csv
+                });
+                continue;
+            }
+
+            if flag == "--help" {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+
+            let mut value = || inline_value.clone().or_else(|| args.next()).ok_or_else(|| format!("{flag} needs a value"));
+
+            match flag.as_str() {
+                "--level" => {
+                    let level = parse(&flag, &value()?)?;
+
+                    if get_level(level).is_none() {
+                        return Err(format!("There is no level {level}"));
+                    }
+
+                    launch_options.level = Some(level);
+                },
+                "--level-file" => launch_options.level_file = Some(read_ron(&value()?)?),
+                "--placements" => launch_options.placements = Some(read_ron(&value()?)?),
+                "--speed" => launch_options.speed = Some(parse::<f32>(&flag, &value()?)?.clamp(MIN_SIMULATION_SPEED, MAX_SIMULATION_SPEED)),
+                "--log-level" => launch_options.log_level = parse(&flag, &value()?)?,
+                "--windowed" => {
+                    let size = value()?;
+                    let (width, height) = size.split_once('x').ok_or_else(|| format!("--windowed expects WxH, got {size}"))?;
+
+                    launch_options.window_size = Some(Vec2::new(parse(&flag, width)?, parse(&flag, height)?));
+                },
+                _ => return Err(format!("Unknown argument {arg}"))
+            }
+        }
+
+        Ok(launch_options)
+    }
+
+    /// Whether to skip the menu and go straight to setting up a level.
+    fn skips_menu(&self) -> bool {
+        self.level.is_some() || self.level_file.is_some() || self.placements.is_some()
+    }
+
+    pub fn window(&self) -> Window {
+        let mut window = Window { title: "Nucleus".into(), ..default() };
+
+        if let Some(size) = self.window_size {
+            window.resolution = WindowResolution::new(size.x, size.y);
+        }
+
+        window
+    }
+}
+
+impl Plugin for LaunchOptions {
+    fn build(&self, app: &mut App) {
+        // only for this launch, unless the settings screen is opened and saves them
+        let mut settings = app.world.resource_mut::<Settings>();
+
+        if let Some(speed) = self.speed {
+            settings.simulation_speed = speed;
+        }
+        if self.window_size.is_some() {
+            settings.window_mode = SettingsWindowMode::Windowed;
+        }
+
+        if let Some(level) = self.level {
+            app.world.resource_mut::<GameStats>().level = level;
+        }
+        if let Some(level) = &self.level_file {
+            app.insert_resource(LevelFile(level.clone()));
+        }
+        if let Some(placements) = &self.placements {
+            app.insert_resource(placements.clone())
+                .add_systems(OnEnter(GameState::SETUP), launch_placements.in_set(LevelSetupSet::Frame).run_if(resource_exists::<Placements>()));
+        }
+
+        if let Some(format) = self.telemetry {
+            app.insert_resource(TelemetryOptions { enabled: true, format });
+        }
+
+        if self.skips_menu() {
+            app.insert_resource(NextState(Some(GameState::SETUP)));
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{flag} can't use {value}"))
+}
+
+fn read_ron<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let contents = fs::read_to_string(path).map_err(|error| format!("Failed to read {path}: {error}"))?;
+
+    ron::from_str(&contents).map_err(|error| format!("Failed to parse {path}: {error}"))
+}
+
+/// Places everything from `--placements` once and starts the run, resets after that are left to the player.
+pub fn launch_placements(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    placements: Res<Placements>,
+    atoms_q: Query<(&Transform, &Collider), With<Atom>>,
+    mut level_stats: ResMut<LevelStats>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    let neutron_collider = Collider::new(NEUTRON_SIZE);
+
+    for neutron in &placements.neutrons {
+        if level_stats.num_neutrons <= 0 {
+            warn!("The level has no neutrons left for the one at {}", neutron.position);
+            continue;
+        }
+
+        if atoms_q.iter().any(|(atom_transform, atom_collider)| is_colliding((&atom_transform.translation.xy(), atom_collider), (&neutron.position, &neutron_collider))) {
+            warn!("Skipped the neutron at {}, it would be inside an atom", neutron.position);
+            continue;
+        }
+
+        let velocity = clamp_vec2_by_length(neutron.velocity, MIN_LAUNCH_SPEED, MAX_LAUNCH_SPEED);
+        spawn_neutron(&mut commands, &mut meshes, &mut materials, neutron.position, velocity, 0);
+        level_stats.num_neutrons -= 1;
+    }
+
+    for &position in &placements.control_rods {
+        if level_stats.num_control_rods <= 0 {
+            warn!("The level has no control rods left for the one at {}", position);
+            continue;
+        }
+
+        spawn_control_rod(&mut commands, &mut meshes, &mut materials, position);
+        level_stats.num_control_rods -= 1;
+    }
+
+    info!("Applied the placements from the command line, starting the run");
+
+    commands.remove_resource::<Placements>();
+    next_state.set(GameState::GAME);
+}
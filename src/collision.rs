@@ -1,6 +1,7 @@
 use bevy::prelude::*;
+use rand::thread_rng;
 
-use crate::{neutron::{calculate_split_trajectories, spawn_neutron}, GameStats, ENERGY_RELEASED};
+use crate::{neutron::{calculate_split_trajectories, spawn_neutron, SplitPattern}, fragment::{DelayedEmission, spawn_fragment}, GameStats, GameState};
 
 #[derive(Component, Debug, Default, Clone)]
 pub struct Collider {
@@ -10,7 +11,7 @@ pub struct Collider {
 impl Collider {
     pub fn new(radius: f32) -> Self {
         Collider {
-            radius: radius
+            radius
         }
     }
 }
@@ -18,8 +19,23 @@ impl Collider {
 #[derive(Event, Debug)]
 pub struct CollisionEvent {
     pub neutron_velocity: Vec2,
+    pub neutron_generation: u32,
     pub atom_position: Vec2,
-    pub num_neutrons: i32
+    pub num_neutrons: i32,
+    /// Energy released by this fission, in joules.
+    pub energy: f64,
+    pub split_pattern: SplitPattern,
+    pub delayed_emission: Option<DelayedEmission>
+}
+
+/// Turns atom hits into fissions, releasing energy and new neutrons.
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CollisionEvent>()
+            .add_systems(Update, collision_listener.run_if(in_state(GameState::GAME)));
+    }
 }
 
 pub fn collision_listener(
@@ -29,6 +45,8 @@ pub fn collision_listener(
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut game_stats: ResMut<GameStats>
 ) {
+    let mut rng = thread_rng();
+
     for collision_event in events.read() {
         debug!(
             "Collison! Neutron velocity: {}, Atom position: {}, Num neutrons: {}", 
@@ -37,13 +55,36 @@ pub fn collision_listener(
             collision_event.num_neutrons
         );
 
-        if let Some(new_vectors) = calculate_split_trajectories(collision_event.neutron_velocity, collision_event.num_neutrons) {
-            for vector in new_vectors {
-                spawn_neutron(&mut commands, &mut meshes, &mut materials, collision_event.atom_position, vector);
+        let num_delayed = collision_event.delayed_emission
+            .map_or(0, |delayed_emission| delayed_emission.num_neutrons.clamp(0, collision_event.num_neutrons));
+        let num_prompt = collision_event.num_neutrons - num_delayed;
+
+        if let Some(delayed_emission) = collision_event.delayed_emission.filter(|_| num_delayed > 0) {
+            spawn_fragment(
+                &mut commands, 
+                &mut meshes, 
+                &mut materials, 
+                collision_event.atom_position, 
+                DelayedEmission { num_neutrons: num_delayed, ..delayed_emission }, 
+                collision_event.neutron_velocity.length(),
+                collision_event.neutron_generation + 1
+            );
+        }
+
+        if num_prompt > 0 {
+            if let Some(new_vectors) = calculate_split_trajectories(
+                collision_event.neutron_velocity, 
+                num_prompt, 
+                &collision_event.split_pattern, 
+                &mut rng
+            ) {
+                for vector in new_vectors {
+                    spawn_neutron(&mut commands, &mut meshes, &mut materials, collision_event.atom_position, vector, collision_event.neutron_generation + 1);
+                }
             }
         }
 
-        game_stats.score += ENERGY_RELEASED;
+        game_stats.score += collision_event.energy;
     }
 }
 
@@ -53,4 +94,26 @@ pub fn is_colliding(pair1: (&Vec2, &Collider), pair2: (&Vec2, &Collider)) -> boo
 
 pub fn is_cords_in_collider(cords: Vec2, collider_pos: Vec2, collider: &Collider) -> bool {
     (collider_pos - cords).length_squared() < collider.radius.powi(2)
-}
\ No newline at end of file
+}
+
+pub fn is_circle_in_rect(circle: (&Vec2, &Collider), rect_pos: Vec2, rect_half_size: Vec2) -> bool {
+    let closest_point = circle.0.clamp(rect_pos - rect_half_size, rect_pos + rect_half_size);
+    (closest_point - circle.0.to_owned()).length_squared() < circle.1.radius.powi(2)
+}
+
+pub fn closest_point_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let segment = end - start;
+    let segment_length_squared = segment.length_squared();
+
+    if segment_length_squared < f32::EPSILON {
+        return start;
+    }
+
+    let t = ((point - start).dot(segment) / segment_length_squared).clamp(0., 1.);
+    start + segment * t
+}
+
+/// Mirrors `velocity` about the surface with the given unit `normal`, scaling the bounced normal component by `restitution`.
+pub fn reflect_velocity(velocity: Vec2, normal: Vec2, restitution: f32) -> Vec2 {
+    velocity - normal * velocity.dot(normal) * (1. + restitution)
+}
@@ -0,0 +1,179 @@
+use std::{fs::{self, File}, io::{self, Write, BufWriter}, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use bevy::prelude::*;
+
+use crate::{collision::{collision_listener, CollisionEvent}, fragment::DecayingFragment, neutron::Neutron, GameStats, in_run, GameState};
+
+const EXPORT_DIRECTORY: &str = "criticality";
+/// Simulated seconds between timeline samples.
+const SAMPLE_INTERVAL: f32 = 0.1;
+/// How far k can stray from 1 and still count as critical.
+const CRITICAL_TOLERANCE: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Regime {
+    Subcritical,
+    Critical,
+    Supercritical
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CriticalitySample {
+    pub time: f32,
+    pub active_neutrons: usize,
+    /// The latest generation whose multiplication factor is final, with that factor.
+    pub multiplication_factor: Option<(usize, f32)>
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Criticality {
+    /// Neutrons born into each generation, delayed neutrons included from the moment their fission happens.
+    pub generation_counts: Vec<u32>,
+    /// Every generation below this one has no neutrons left that could still fission.
+    pub completed_generations: usize,
+    pub samples: Vec<CriticalitySample>,
+    next_sample_time: f32
+}
+
+impl Regime {
+    pub fn from_multiplication_factor(k: f32) -> Self {
+        if k < 1. - CRITICAL_TOLERANCE {
+            Regime::Subcritical
+        } else if k > 1. + CRITICAL_TOLERANCE {
+            Regime::Supercritical
+        } else {
+            Regime::Critical
+        }
+    }
+}
+
+impl Criticality {
+    fn record_births(&mut self, generation: usize, num_neutrons: u32) {
+        if self.generation_counts.len() <= generation {
+            self.generation_counts.resize(generation + 1, 0);
+        }
+
+        self.generation_counts[generation] += num_neutrons;
+    }
+
+    /// Neutrons in generation `generation + 1` per neutron in `generation`.
+    pub fn multiplication_factor(&self, generation: usize) -> Option<f32> {
+        let parents = *self.generation_counts.get(generation)?;
+
+        if parents == 0 {
+            return None;
+        }
+
+        Some(self.generation_counts.get(generation + 1).copied().unwrap_or(0) as f32 / parents as f32)
+    }
+
+    /// The multiplication factor of the latest generation that can no longer change.
+    pub fn latest_multiplication_factor(&self) -> Option<(usize, f32)> {
+        let generation = self.completed_generations.checked_sub(1)?;
+        self.multiplication_factor(generation).map(|k| (generation, k))
+    }
+
+    /// Writes the generations and the timeline to their own CSV files under `criticality/`.
+    pub fn export(&self) -> io::Result<(PathBuf, PathBuf)> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default();
+        let generations_path = PathBuf::from(EXPORT_DIRECTORY).join(format!("{timestamp}-generations.csv"));
+        let timeline_path = PathBuf::from(EXPORT_DIRECTORY).join(format!("{timestamp}-timeline.csv"));
+
+        fs::create_dir_all(EXPORT_DIRECTORY)?;
+
+        let mut generations_file = BufWriter::new(File::create(&generations_path)?);
+        writeln!(generations_file, "generation,neutrons,k")?;
+
+        for (generation, neutrons) in self.generation_counts.iter().enumerate() {
+            let k = self.multiplication_factor(generation).filter(|_| generation < self.completed_generations);
+            writeln!(generations_file, "{generation},{neutrons},{}", k.map(|k| k.to_string()).unwrap_or_default())?;
+        }
+
+        let mut timeline_file = BufWriter::new(File::create(&timeline_path)?);
+        writeln!(timeline_file, "time,active_neutrons,generation,k")?;
+
+        for sample in &self.samples {
+            let (generation, k) = sample.multiplication_factor
+                .map(|(generation, k)| (generation.to_string(), k.to_string()))
+                .unwrap_or_default();
+
+            writeln!(timeline_file, "{},{},{generation},{k}", sample.time, sample.active_neutrons)?;
+        }
+
+        generations_file.flush()?;
+        timeline_file.flush()?;
+
+        Ok((generations_path, timeline_path))
+    }
+}
+
+pub struct CriticalityPlugin;
+
+impl Plugin for CriticalityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Criticality>()
+            .add_systems(OnTransition { from: GameState::SETUP, to: GameState::GAME }, criticality_setup)
+            // births are recorded before generations are closed off, and only once the split neutrons exist
+            .add_systems(Update, (criticality_listener, criticality_update).chain().after(collision_listener).run_if(in_state(GameState::GAME)))
+            .add_systems(Update, criticality_export.run_if(in_run));
+    }
+}
+
+pub fn criticality_setup(
+    mut commands: Commands,
+    neutron_q: Query<&Neutron>
+) {
+    let mut criticality = Criticality::default();
+    criticality.record_births(0, neutron_q.iter().count() as u32);
+
+    commands.insert_resource(criticality);
+}
+
+pub fn criticality_listener(
+    mut events: EventReader<CollisionEvent>,
+    mut criticality: ResMut<Criticality>
+) {
+    for collision_event in events.read() {
+        criticality.record_births(collision_event.neutron_generation as usize + 1, collision_event.num_neutrons.max(0) as u32);
+    }
+}
+
+pub fn criticality_update(
+    neutron_q: Query<&Neutron>,
+    fragment_q: Query<&DecayingFragment>,
+    game_stats: Res<GameStats>,
+    mut criticality: ResMut<Criticality>
+) {
+    // a neutron of generation g can still add to generation g + 1, and so can the neutrons a fragment is holding back
+    let oldest_live_generation = neutron_q.iter().map(|neutron| neutron.generation as usize)
+        .chain(fragment_q.iter().map(|fragment| fragment.generation as usize))
+        .min()
+        .unwrap_or(criticality.generation_counts.len());
+
+    criticality.completed_generations = oldest_live_generation;
+
+    if game_stats.simulation_time >= criticality.next_sample_time {
+        let sample = CriticalitySample {
+            time: game_stats.simulation_time,
+            active_neutrons: neutron_q.iter().count(),
+            multiplication_factor: criticality.latest_multiplication_factor()
+        };
+
+        criticality.samples.push(sample);
+        criticality.next_sample_time = game_stats.simulation_time + SAMPLE_INTERVAL;
+    }
+}
+
+pub fn criticality_export(
+    keyboard_input: Res<Input<KeyCode>>,
+    criticality: Res<Criticality>
+) {
+    if !keyboard_input.just_pressed(KeyCode::X) {
+        return;
+    }
+
+    match criticality.export() {
+        Ok((generations_path, timeline_path)) => info!("Exported criticality to {} and {}", generations_path.display(), timeline_path.display()),
+        Err(error) => error!("Failed to export criticality: {}", error)
+    }
+}
@@ -0,0 +1,232 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, render::render_resource::{Extent3d, TextureDimension, TextureFormat}};
+use rand::{thread_rng, Rng};
+
+use crate::{collision::CollisionEvent, level_manager::LevelSetupSet, GameState, GameStats, in_run};
+
+/// Sprites shared by every fission effect. Once they are all in use new fissions go without effects
+/// rather than spawning more, which keeps huge chain reactions running smoothly.
+const EFFECT_POOL_SIZE: usize = 4096;
+const EFFECT_TEXTURE_SIZE: u32 = 64;
+const EFFECT_Z: f32 = 3.;
+
+const SHOCKWAVE_LIFETIME: f32 = 0.5;
+const SHOCKWAVE_START_SIZE: f32 = 20.;
+const SHOCKWAVE_END_SIZE: f32 = 140.;
+const SHOCKWAVE_COLOR: Color = Color::rgb(1.0, 0.8, 0.4);
+
+const FLASH_LIFETIME: f32 = 0.15;
+const FLASH_SIZE: f32 = 120.;
+const FLASH_COLOR: Color = Color::rgb(1.0, 1.0, 0.85);
+/// Neutrons released by a fission whose flash and shockwave are drawn at their base size.
+const FLASH_BASE_NEUTRONS: f32 = 2.;
+
+const PARTICLE_LIFETIME: f32 = 0.6;
+const PARTICLE_SIZE: f32 = 10.;
+const PARTICLE_SPEED: f32 = 150.;
+const PARTICLES_PER_FISSION: usize = 3;
+const PARTICLE_COLOR: Color = Color::ORANGE;
+
+#[derive(Resource, Debug)]
+pub struct EffectsOptions {
+    pub enabled: bool
+}
+
+impl Default for EffectsOptions {
+    fn default() -> Self {
+        EffectsOptions { enabled: true }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct EffectPool {
+    free_rings: Vec<Entity>,
+    free_glows: Vec<Entity>
+}
+
+#[derive(Component, Debug, Default)]
+pub struct FissionEffect {
+    active: bool,
+    is_ring: bool,
+    age: f32,
+    lifetime: f32,
+    velocity: Vec2,
+    start_size: f32,
+    end_size: f32,
+    color: Color
+}
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EffectsOptions>()
+            .add_systems(Startup, effects_setup)
+            .add_systems(OnEnter(GameState::SETUP), effects_reset.in_set(LevelSetupSet::Spawn))
+            .add_systems(Update, spawn_fission_effects.run_if(in_state(GameState::GAME)))
+            .add_systems(Update, update_fission_effects.run_if(in_run.and_then(not(in_state(GameState::PAUSED)))))
+            .add_systems(Update, (effects_toggle, effects_reset.run_if(effects_disabled)));
+    }
+}
+
+pub fn effects_setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>
+) {
+    let glow = images.add(radial_texture(|distance| (1. - distance).max(0.).powi(2)));
+    let ring = images.add(radial_texture(|distance| (-((distance - 0.85) / 0.08).powi(2)).exp()));
+
+    let mut effect_pool = EffectPool::default();
+
+    for i in 0..EFFECT_POOL_SIZE {
+        // a fission takes one ring and the rest glows, so split the pool the same way
+        let is_ring = i % (PARTICLES_PER_FISSION + 2) == 0;
+
+        let effect_entity = commands.spawn((
+            SpriteBundle {
+                texture: if is_ring { ring.clone() } else { glow.clone() },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            FissionEffect { is_ring, ..default() }
+        )).id();
+
+        if is_ring {
+            effect_pool.free_rings.push(effect_entity);
+        } else {
+            effect_pool.free_glows.push(effect_entity);
+        }
+    }
+
+    commands.insert_resource(effect_pool);
+}
+
+/// A white texture whose alpha is `alpha(distance from centre)`, where the distance is 1 at the edge.
+fn radial_texture(alpha: impl Fn(f32) -> f32) -> Image {
+    let centre = (EFFECT_TEXTURE_SIZE as f32 - 1.) / 2.;
+    let mut data = Vec::with_capacity((EFFECT_TEXTURE_SIZE * EFFECT_TEXTURE_SIZE * 4) as usize);
+
+    for y in 0..EFFECT_TEXTURE_SIZE {
+        for x in 0..EFFECT_TEXTURE_SIZE {
+            let distance = Vec2::new(x as f32 - centre, y as f32 - centre).length() / centre;
+            data.extend_from_slice(&[255, 255, 255, (alpha(distance).clamp(0., 1.) * 255.) as u8]);
+        }
+    }
+
+    Image::new(
+        Extent3d { width: EFFECT_TEXTURE_SIZE, height: EFFECT_TEXTURE_SIZE, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb
+    )
+}
+
+pub fn spawn_fission_effects(
+    mut events: EventReader<CollisionEvent>,
+    mut effect_pool: ResMut<EffectPool>,
+    mut effect_q: Query<(&mut Transform, &mut Sprite, &mut Visibility, &mut FissionEffect)>,
+    effects_options: Res<EffectsOptions>
+) {
+    if !effects_options.enabled {
+        events.clear();
+        return;
+    }
+
+    let mut rng = thread_rng();
+
+    for collision_event in events.read() {
+        // atoms that release more neutrons go off bigger
+        let yield_scale = (collision_event.num_neutrons.max(1) as f32 / FLASH_BASE_NEUTRONS).sqrt();
+        let mut effects = vec![
+            FissionEffect { is_ring: true, lifetime: SHOCKWAVE_LIFETIME, start_size: SHOCKWAVE_START_SIZE, end_size: SHOCKWAVE_END_SIZE * yield_scale, color: SHOCKWAVE_COLOR, ..default() },
+            FissionEffect { lifetime: FLASH_LIFETIME, start_size: FLASH_SIZE * yield_scale, end_size: FLASH_SIZE * yield_scale * 0.5, color: FLASH_COLOR, ..default() }
+        ];
+
+        for _ in 0..PARTICLES_PER_FISSION {
+            let velocity = Vec2::from_angle(rng.gen_range(0.0..TAU)) * PARTICLE_SPEED * rng.gen_range(0.5..1.5);
+            effects.push(FissionEffect { velocity, lifetime: PARTICLE_LIFETIME, start_size: PARTICLE_SIZE, end_size: PARTICLE_SIZE * 0.25, color: PARTICLE_COLOR, ..default() });
+        }
+
+        for new_effect in effects {
+            let free = if new_effect.is_ring { &mut effect_pool.free_rings } else { &mut effect_pool.free_glows };
+
+            let Some(effect_entity) = free.pop() else {
+                continue;
+            };
+
+            if let Ok((mut transform, mut sprite, mut visibility, mut effect)) = effect_q.get_mut(effect_entity) {
+                transform.translation = Vec3::from((collision_event.atom_position, EFFECT_Z));
+                sprite.color = new_effect.color;
+                sprite.custom_size = Some(Vec2::splat(new_effect.start_size));
+                *visibility = Visibility::Inherited;
+                *effect = FissionEffect { active: true, ..new_effect };
+            }
+        }
+    }
+}
+
+/// Plays out in simulated time, like the reaction itself.
+pub fn update_fission_effects(
+    mut effect_pool: ResMut<EffectPool>,
+    mut effect_q: Query<(Entity, &mut Transform, &mut Sprite, &mut Visibility, &mut FissionEffect)>,
+    game_stats: Res<GameStats>,
+    time: Res<Time>
+) {
+    let delta = time.delta_seconds() * game_stats.simulation_speed;
+
+    for (effect_entity, mut transform, mut sprite, mut visibility, mut effect) in &mut effect_q {
+        if !effect.active {
+            continue;
+        }
+
+        effect.age += delta;
+
+        if effect.age >= effect.lifetime {
+            release_effect(&mut effect_pool, effect_entity, &mut visibility, &mut effect);
+            continue;
+        }
+
+        let progress = effect.age / effect.lifetime;
+
+        transform.translation += Vec3::from((effect.velocity * delta, 0.));
+        sprite.custom_size = Some(Vec2::splat(effect.start_size + (effect.end_size - effect.start_size) * progress));
+        sprite.color = effect.color.with_a(1. - progress);
+    }
+}
+
+pub fn effects_toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut effects_options: ResMut<EffectsOptions>
+) {
+    if keyboard_input.just_pressed(KeyCode::V) {
+        effects_options.enabled = !effects_options.enabled;
+    }
+}
+
+/// Hides every running effect, for a fresh level or when effects get switched off.
+pub fn effects_reset(
+    mut effect_pool: ResMut<EffectPool>,
+    mut effect_q: Query<(Entity, &mut Visibility, &mut FissionEffect)>
+) {
+    for (effect_entity, mut visibility, mut effect) in &mut effect_q {
+        if effect.active {
+            release_effect(&mut effect_pool, effect_entity, &mut visibility, &mut effect);
+        }
+    }
+}
+
+pub fn effects_disabled(effects_options: Res<EffectsOptions>) -> bool {
+    effects_options.is_changed() && !effects_options.enabled
+}
+
+fn release_effect(effect_pool: &mut EffectPool, effect_entity: Entity, visibility: &mut Visibility, effect: &mut FissionEffect) {
+    effect.active = false;
+    *visibility = Visibility::Hidden;
+
+    if effect.is_ring {
+        effect_pool.free_rings.push(effect_entity);
+    } else {
+        effect_pool.free_glows.push(effect_entity);
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::BTreeMap, f32::consts::TAU, fs, io, time::{SystemTime, UNIX_EPOCH}};
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*, tasks::{block_on, AsyncComputeTaskPool, Task}};
+use rand::{prelude::*, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{atom::ATOM_SIZE, cli::{launch_placements, PlacedNeutron, Placements}, generator::{Generator, Layout, NeutronCounts}, headless_app, hud::results_setup, level_manager::{Level, LevelFile, LevelStats}, settings::Settings, wall::Boundary, GameState, GameStats, ENERGY_RELEASED};
+
+const ENDLESS_SCORES_PATH: &str = "endless_scores.ron";
+/// Shots tried when looking for a level's target score.
+const SEARCH_SHOTS: usize = 16;
+/// Ticks a searched shot gets before its score so far is taken.
+const SEARCH_MAX_TICKS: usize = 1200;
+/// Faster than anyone plays, but slow enough that neutrons can't skip over atoms.
+const SEARCH_SIMULATION_SPEED: f32 = 2.;
+const SEARCH_LAUNCH_SPEED: f32 = 750.;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The seed of the generated level being played, if it is one.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndlessSeed {
+    pub seed: u64,
+    pub daily: bool
+}
+
+/// A level being generated and searched for its target score off the main thread.
+#[derive(Resource)]
+pub struct EndlessSearch {
+    seed: EndlessSeed,
+    task: Task<Level>
+}
+
+/// Best energy reached for every seed played, saved to `endless_scores.ron` after each run.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct EndlessScores {
+    pub best: BTreeMap<u64, f64>
+}
+
+/// How the last endless run compared with the seed's best before it.
+#[derive(Resource, Debug, Default)]
+pub struct EndlessResult {
+    /// Beat the previous best, or released anything at all on a first play.
+    pub new_best: bool
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndlessButton {
+    Daily,
+    Random
+}
+
+#[derive(Component)]
+pub struct EndlessStatusText;
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EndlessScores::load())
+            .init_resource::<EndlessResult>()
+            .add_systems(Update, (endless_buttons, endless_search_poll).run_if(in_state(GameState::MENU)))
+            .add_systems(OnEnter(GameState::RESULTS), endless_record.before(results_setup).run_if(resource_exists::<EndlessSeed>()));
+    }
+}
+
+impl EndlessSeed {
+    /// The same for everyone on the same day, counted in days since 1970 (UTC).
+    pub fn daily() -> Self {
+        let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() / SECONDS_PER_DAY).unwrap_or_default();
+
+        EndlessSeed { seed: days, daily: true }
+    }
+
+    pub fn random() -> Self {
+        // short enough to read out to someone else
+        EndlessSeed { seed: thread_rng().gen::<u32>() as u64, daily: false }
+    }
+
+    pub fn label(&self) -> String {
+        if self.daily { format!("Daily #{}", self.seed) } else { format!("Seed {}", self.seed) }
+    }
+}
+
+impl EndlessScores {
+    /// Reads the scores file, starting afresh if it is missing or can't be parsed.
+    pub fn load() -> Self {
+        match fs::read_to_string(ENDLESS_SCORES_PATH) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+                error!("Failed to parse {}, starting with no endless scores: {}", ENDLESS_SCORES_PATH, error);
+                EndlessScores::default()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => EndlessScores::default(),
+            Err(error) => {
+                error!("Failed to read {}, starting with no endless scores: {}", ENDLESS_SCORES_PATH, error);
+                EndlessScores::default()
+            }
+        }
+    }
+
+    pub fn best(&self, seed: u64) -> Option<f64> {
+        self.best.get(&seed).copied()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        fs::write(ENDLESS_SCORES_PATH, contents)
+    }
+}
+
+/// A random layout, neutron mix and maybe a boundary, all picked from the seed. The target score is left at 0.
+pub fn generate_endless_level(seed: u64) -> Level {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let spacing = rng.gen_range(70. ..110.);
+
+    let layout = match rng.gen_range(0..6) {
+        0 => Layout::Grid { radius: rng.gen_range(300. ..600.), spacing },
+        1 => Layout::Hex { radius: rng.gen_range(300. ..700.), spacing },
+        2 => Layout::Poisson { radius: rng.gen_range(300. ..700.), spacing },
+        3 => Layout::Spiral { count: rng.gen_range(40..160), spacing },
+        4 => Layout::Rings { rings: rng.gen_range(3..7), spacing },
+        _ => Layout::Islands { islands: rng.gen_range(3..7), island_radius: rng.gen_range(120. ..220.), radius: rng.gen_range(700. ..1100.), spacing }
+    };
+
+    let neutrons = NeutronCounts::Weighted((1..=4).map(|count| (count, rng.gen_range(0.1..2.) / count as f32)).collect());
+
+    let boundary = rng.gen_bool(0.5).then(|| Boundary::Circle { radius: layout.extent() + rng.gen_range(150. ..400.), restitution: rng.gen_range(0.6..1.) });
+
+    Level {
+        atoms: vec![],
+        generators: vec![Generator::new(layout, neutrons, rng.gen())],
+        absorbers: vec![],
+        walls: vec![],
+        boundary,
+        split_patterns: vec![],
+        objectives: vec![],
+        level_stats: LevelStats::new(1, 0, 0.)
+    }
+}
+
+/// The best energy out of a handful of shots aimed at random atoms, which makes a target that can be reached.
+pub fn search_s_score(level: &Level, seed: u64) -> f64 {
+    let atoms = level.all_atoms();
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+
+    let best = (0..SEARCH_SHOTS)
+        .filter_map(|_| {
+            let target = atoms.choose(&mut rng)?;
+            let direction = Vec2::from_angle(rng.gen_range(0. ..TAU));
+
+            Some(PlacedNeutron { position: target.position - direction * ATOM_SIZE * 3., velocity: direction * SEARCH_LAUNCH_SPEED })
+        })
+        .map(|shot| simulate_shot(level, shot))
+        .fold(0., f64::max);
+
+    best.max(ENERGY_RELEASED)
+}
+
+/// Plays one shot headlessly, returning the energy released once the reaction dies out or runs out of ticks.
+pub fn simulate_shot(level: &Level, shot: PlacedNeutron) -> f64 {
+    let mut app = headless_app();
+
+    app.world.resource_mut::<Settings>().simulation_speed = SEARCH_SIMULATION_SPEED;
+    app.insert_resource(LevelFile(level.clone()))
+        .insert_resource(NextState(Some(GameState::SETUP)));
+    app.update();
+
+    app.insert_resource(Placements { neutrons: vec![shot], control_rods: vec![] });
+    app.world.run_system_once(launch_placements);
+
+    for _ in 0..SEARCH_MAX_TICKS {
+        app.update();
+
+        if app.world.resource::<State<GameState>>().get().eq(&GameState::RESULTS) {
+            break;
+        }
+    }
+
+    app.world.resource::<GameStats>().score
+}
+
+pub fn endless_buttons(
+    mut commands: Commands,
+    interaction_q: Query<(&Interaction, &EndlessButton), Changed<Interaction>>,
+    endless_search: Option<Res<EndlessSearch>>,
+    mut status_text_q: Query<&mut Text, With<EndlessStatusText>>
+) {
+    if endless_search.is_some() {
+        return;
+    }
+
+    for (interaction, endless_button) in &interaction_q {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let seed = match endless_button {
+            EndlessButton::Daily => EndlessSeed::daily(),
+            EndlessButton::Random => EndlessSeed::random()
+        };
+
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let mut level = generate_endless_level(seed.seed);
+            level.level_stats.s_score = search_s_score(&level, seed.seed);
+            level
+        });
+
+        commands.insert_resource(EndlessSearch { seed, task });
+
+        for mut text in &mut status_text_q {
+            text.sections[0].value = format!("Generating {}...", seed.label());
+        }
+
+        info!("Generating endless level for {}", seed.label());
+    }
+}
+
+/// Starts the generated level once its target score has been found.
+pub fn endless_search_poll(
+    mut commands: Commands,
+    endless_search: Option<Res<EndlessSearch>>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    if !endless_search.is_some_and(|endless_search| endless_search.task.is_finished()) {
+        return;
+    }
+
+    commands.add(|world: &mut World| {
+        let Some(EndlessSearch { seed, task }) = world.remove_resource::<EndlessSearch>() else {
+            return;
+        };
+
+        // already finished, so this returns straight away
+        let level = block_on(task);
+        info!("Generated {} with a target of {:.12} J", seed.label(), level.level_stats.s_score);
+
+        world.insert_resource(LevelFile(level));
+        world.insert_resource(seed);
+    });
+    next_state.set(GameState::SETUP);
+}
+
+/// Keeps the best score for the seed, for the results screen to show.
+pub fn endless_record(
+    endless_seed: Res<EndlessSeed>,
+    mut endless_scores: ResMut<EndlessScores>,
+    mut endless_result: ResMut<EndlessResult>,
+    game_stats: Res<GameStats>
+) {
+    endless_result.new_best = game_stats.score > endless_scores.best(endless_seed.seed).unwrap_or(0.);
+
+    if !endless_result.new_best {
+        return;
+    }
+
+    endless_scores.best.insert(endless_seed.seed, game_stats.score);
+
+    match endless_scores.save() {
+        Ok(()) => info!("New best of {:.12} J for {}", game_stats.score, endless_seed.label()),
+        Err(error) => error!("Failed to save endless scores to {}: {}", ENDLESS_SCORES_PATH, error)
+    }
+}
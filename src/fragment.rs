@@ -0,0 +1,101 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use rand::{thread_rng, Rng};
+use serde::Deserialize;
+
+use crate::{neutron::{calculate_split_trajectories, spawn_neutron, SplitPattern, SplitShape}, GameStats};
+
+const FRAGMENT_SIZE: f32 = 18.;
+const FRAGMENT_COLOR: Color = Color::rgb(1.0, 0.45, 0.1);
+/// Glow pulses per simulated second.
+const FRAGMENT_PULSE_RATE: f32 = 4.;
+
+/// Some of an atom's neutrons are held back after fission and released by its fragments `delay` simulated seconds later.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DelayedEmission {
+    pub num_neutrons: i32,
+    pub delay: f32
+}
+
+#[derive(Component, Debug)]
+pub struct DecayingFragment {
+    timer: Timer,
+    num_neutrons: i32,
+    speed: f32,
+    /// Generation of the neutrons this fragment will release.
+    pub generation: u32
+}
+
+pub fn spawn_fragment(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    position: Vec2,
+    delayed_emission: DelayedEmission,
+    speed: f32,
+    generation: u32
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(FRAGMENT_SIZE).into()).into(),
+            material: materials.add(ColorMaterial::from(FRAGMENT_COLOR)),
+            transform: Transform { translation: Vec3::from((position, 2.)), ..default() },
+            ..default()
+        },
+        DecayingFragment {
+            timer: Timer::from_seconds(delayed_emission.delay, TimerMode::Once),
+            num_neutrons: delayed_emission.num_neutrons,
+            speed,
+            generation
+        }
+    ));
+}
+
+pub fn fragment_decay(
+    mut commands: Commands,
+    mut fragment_q: Query<(Entity, &Transform, &mut DecayingFragment, &Handle<ColorMaterial>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+    game_stats: Res<GameStats>
+) {
+    let mut rng = thread_rng();
+
+    for (fragment_entity, fragment_transform, mut fragment, material_handle) in &mut fragment_q {
+        fragment.timer.tick(time.delta().mul_f32(game_stats.simulation_speed));
+
+        if !fragment.timer.finished() {
+            let remaining = 1. - fragment.timer.percent();
+            let pulse = 0.75 + 0.25 * (fragment.timer.elapsed_secs() * FRAGMENT_PULSE_RATE * TAU).sin();
+
+            if let Some(material) = materials.get_mut(material_handle) {
+                material.color = FRAGMENT_COLOR.with_a(0.25 + 0.75 * remaining * pulse);
+            }
+
+            continue;
+        }
+
+        // delayed neutrons carry no memory of the original neutron's direction, so spread them evenly from a random start
+        let direction = Vec2::from_angle(rng.gen_range(0.0..TAU));
+        let split_pattern = SplitPattern {
+            shape: SplitShape::Symmetric { spread: TAU * (fragment.num_neutrons - 1) as f32 / fragment.num_neutrons as f32 },
+            ..default()
+        };
+
+        if let Some(new_vectors) = calculate_split_trajectories(direction * fragment.speed, fragment.num_neutrons, &split_pattern, &mut rng) {
+            for vector in new_vectors {
+                spawn_neutron(&mut commands, &mut meshes, &mut materials, fragment_transform.translation.xy(), vector, fragment.generation);
+            }
+        }
+
+        commands.entity(fragment_entity).despawn_recursive();
+    }
+}
+
+pub fn fragment_cleanup(
+    mut commands: Commands,
+    fragment_q: Query<Entity, With<DecayingFragment>>
+) {
+    fragment_q.for_each(|fragment_entity| commands.entity(fragment_entity).despawn_recursive());
+}
@@ -0,0 +1,274 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+use crate::{input::VirtualCursor, neutron::{Neutron, PlacementMarker}, player_controls::{CameraOptions, PlacementState, KEY_ZOOM_RATE, MAX_ZOOM_IN, MAX_ZOOM_OUT}, util::clamp, MainCamera, in_level, GameState};
+
+/// Pixels per second the virtual cursor moves at full tilt.
+const CURSOR_SPEED: f32 = 600.;
+const CURSOR_SIZE: f32 = 14.;
+/// How far ahead of a neutron full tilt aims while setting its velocity, which is also the fastest launch.
+const AIM_DISTANCE: f32 = 200.;
+
+#[derive(Component)]
+pub struct VirtualCursorNode;
+
+/// The button the d-pad has moved to, pressed with A. Also the UI node A is holding down, if any.
+#[derive(Resource, Debug, Default)]
+pub struct GamepadFocus {
+    pub button: Option<Entity>,
+    pressed: Option<Entity>
+}
+
+/// True if `position`, in window coordinates, is inside the node.
+pub fn node_contains(node: &Node, transform: &GlobalTransform, position: Vec2) -> bool {
+    node.logical_rect(transform).contains(position)
+}
+
+/// The left stick of every connected gamepad added together, y pointing up.
+fn left_stick(gamepads: &Gamepads, axes: &Axis<GamepadAxis>) -> Vec2 {
+    stick(gamepads, axes, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY)
+}
+
+fn stick(gamepads: &Gamepads, axes: &Axis<GamepadAxis>, x_axis: GamepadAxisType, y_axis: GamepadAxisType) -> Vec2 {
+    gamepads.iter().map(|gamepad| Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, x_axis)).unwrap_or_default(),
+        axes.get(GamepadAxis::new(gamepad, y_axis)).unwrap_or_default()
+    )).sum()
+}
+
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GamepadFocus>()
+            .add_systems(Startup, virtual_cursor_setup)
+            .add_systems(OnEnter(GameState::MENU), virtual_cursor_reset)
+            .add_systems(Update, (gamepad_cursor, gamepad_camera).run_if(in_level))
+            // before the button systems see the interaction, and after bevy has set it from the mouse
+            .add_systems(PreUpdate, (gamepad_focus_navigate, gamepad_focus_press).chain().after(UiSystem::Focus))
+            .add_systems(Update, (virtual_cursor_draw, gamepad_focus_draw));
+    }
+}
+
+pub fn virtual_cursor_setup(
+    mut commands: Commands
+) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Px(CURSOR_SIZE),
+                height: Val::Px(CURSOR_SIZE),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            border_color: Color::ORANGE.into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        VirtualCursorNode
+    ));
+}
+
+/// Moves the virtual cursor with the left stick, or aims the neutron being placed with it.
+/// Touching the mouse hands control back to the real cursor.
+#[allow(clippy::too_many_arguments)]
+pub fn gamepad_cursor(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    window_q: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    marked_neutron_q: Query<&GlobalTransform, (With<Neutron>, With<PlacementMarker>)>,
+    placement_state: Res<State<PlacementState>>,
+    mut virtual_cursor: ResMut<VirtualCursor>,
+    mut gamepad_focus: ResMut<GamepadFocus>,
+    time: Res<Time>
+) {
+    if cursor_moved_events.read().count() > 0 {
+        virtual_cursor.active = false;
+    }
+
+    let stick = left_stick(&gamepads, &axes);
+
+    if stick == Vec2::ZERO {
+        return;
+    }
+
+    if gamepad_focus.button.is_some() {
+        gamepad_focus.button = None;
+    }
+
+    let window = window_q.single();
+    let window_size = Vec2::new(window.width(), window.height());
+
+    if !virtual_cursor.active {
+        virtual_cursor.position = window.cursor_position().unwrap_or(window_size / 2.);
+        virtual_cursor.active = true;
+    }
+
+    if placement_state.get().eq(&PlacementState::VELOCITY) {
+        let (camera, camera_transform) = camera_q.single();
+
+        if let Some(aim) = marked_neutron_q.get_single().ok()
+            .and_then(|neutron_transform| camera.world_to_viewport(camera_transform, (neutron_transform.translation().xy() + stick.clamp_length_max(1.) * AIM_DISTANCE).extend(0.))) {
+            virtual_cursor.position = aim;
+            return;
+        }
+    }
+
+    let position = virtual_cursor.position + stick * Vec2::new(1., -1.) * CURSOR_SPEED * time.delta_seconds();
+    virtual_cursor.position = position.clamp(Vec2::ZERO, window_size);
+}
+
+pub fn virtual_cursor_draw(
+    mut cursor_node_q: Query<(&mut Style, &mut Visibility), With<VirtualCursorNode>>,
+    virtual_cursor: Res<VirtualCursor>
+) {
+    if !virtual_cursor.is_changed() {
+        return;
+    }
+
+    for (mut style, mut visibility) in &mut cursor_node_q {
+        style.left = Val::Px(virtual_cursor.position.x - CURSOR_SIZE / 2.);
+        style.top = Val::Px(virtual_cursor.position.y - CURSOR_SIZE / 2.);
+        *visibility = if virtual_cursor.active { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+/// The menus are used with the d-pad rather than the virtual cursor, so hand the mouse back on the way there.
+pub fn virtual_cursor_reset(
+    mut virtual_cursor: ResMut<VirtualCursor>
+) {
+    virtual_cursor.active = false;
+}
+
+/// Right stick pans, the right trigger zooms in and the left trigger zooms out.
+pub fn gamepad_camera(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    trigger_axes: Res<Axis<GamepadButton>>,
+    camera_options: Res<CameraOptions>,
+    time: Res<Time>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>
+) {
+    let mut camera_transform = cameras.single_mut();
+
+    let pan = stick(&gamepads, &axes, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+    let zoom: f32 = gamepads.iter().map(|gamepad| {
+        let trigger = |button_type| trigger_axes.get(GamepadButton::new(gamepad, button_type)).unwrap_or_default();
+        trigger(GamepadButtonType::LeftTrigger2) - trigger(GamepadButtonType::RightTrigger2)
+    }).sum();
+
+    let scale = camera_transform.scale.x;
+    camera_transform.translation += Vec3::from((pan * camera_options.movement_speed * scale * time.delta_seconds(), 0.));
+
+    if zoom != 0. {
+        camera_transform.scale = Vec3::splat(clamp(scale + zoom * KEY_ZOOM_RATE * camera_options.zoom_speed * time.delta_seconds(), MAX_ZOOM_IN, MAX_ZOOM_OUT));
+    }
+}
+
+/// The d-pad moves focus to the nearest button in that direction, the first press focuses the top left one.
+/// Moving the mouse, or the button going away, drops focus.
+pub fn gamepad_focus_navigate(
+    gamepads: Res<Gamepads>,
+    gamepad_button_input: Res<Input<GamepadButton>>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    button_q: Query<(Entity, &GlobalTransform, &ViewVisibility), With<Button>>,
+    mut gamepad_focus: ResMut<GamepadFocus>
+) {
+    let moved = cursor_moved_events.read().count() > 0;
+    let gone = gamepad_focus.button.is_some_and(|focused| !button_q.get(focused).is_ok_and(|(_, _, view_visibility)| view_visibility.get()));
+
+    if gamepad_focus.button.is_some() && (moved || gone) {
+        gamepad_focus.button = None;
+    }
+
+    let just_pressed = |button_type| gamepads.iter().any(|gamepad| gamepad_button_input.just_pressed(GamepadButton::new(gamepad, button_type)));
+
+    // y points down in the UI
+    let direction = [
+        (GamepadButtonType::DPadUp, Vec2::NEG_Y),
+        (GamepadButtonType::DPadDown, Vec2::Y),
+        (GamepadButtonType::DPadLeft, Vec2::NEG_X),
+        (GamepadButtonType::DPadRight, Vec2::X)
+    ].into_iter().find(|(button_type, _)| just_pressed(*button_type)).map(|(_, direction)| direction);
+
+    let Some(direction) = direction else {
+        return;
+    };
+
+    let buttons = || button_q.iter()
+        .filter(|(_, _, view_visibility)| view_visibility.get())
+        .map(|(entity, transform, _)| (entity, transform.translation().xy()));
+
+    let current = gamepad_focus.button.and_then(|focused| buttons().find(|(entity, _)| *entity == focused));
+
+    let next = match current {
+        // anything within 45 degrees of the direction, closest first
+        Some((_, from)) => buttons()
+            .filter(|(_, position)| (*position - from).dot(direction) > (*position - from).perp_dot(direction).abs())
+            .min_by(|(_, a), (_, b)| a.distance_squared(from).total_cmp(&b.distance_squared(from)))
+            .map(|(entity, _)| entity),
+        None => buttons()
+            .min_by(|(_, a), (_, b)| (a.x + a.y).total_cmp(&(b.x + b.y)))
+            .map(|(entity, _)| entity)
+    };
+
+    if next.is_some() {
+        gamepad_focus.button = next;
+    }
+}
+
+/// A presses the focused button, or whatever UI the virtual cursor is over, by setting its `Interaction` like a click would.
+pub fn gamepad_focus_press(
+    gamepads: Res<Gamepads>,
+    gamepad_button_input: Res<Input<GamepadButton>>,
+    virtual_cursor: Res<VirtualCursor>,
+    mut interaction_q: Query<(Entity, &mut Interaction, &Node, &GlobalTransform, &ViewVisibility)>,
+    mut gamepad_focus: ResMut<GamepadFocus>
+) {
+    let south = |gamepad| GamepadButton::new(gamepad, GamepadButtonType::South);
+
+    if gamepads.iter().any(|gamepad| gamepad_button_input.just_pressed(south(gamepad))) {
+        gamepad_focus.pressed = gamepad_focus.button.or_else(|| {
+            virtual_cursor.active.then(|| interaction_q.iter()
+                .find(|(_, _, node, transform, view_visibility)| view_visibility.get() && node_contains(node, transform, virtual_cursor.position))
+                .map(|(entity, ..)| entity)
+            ).flatten()
+        });
+    }
+
+    let Some(pressed) = gamepad_focus.pressed else {
+        return;
+    };
+
+    let held = gamepads.iter().any(|gamepad| gamepad_button_input.pressed(south(gamepad)));
+
+    if let Ok((_, mut interaction, ..)) = interaction_q.get_mut(pressed) {
+        // bevy lets go of presses when the mouse leaves the window, so keep holding it
+        interaction.set_if_neq(if held { Interaction::Pressed } else { Interaction::None });
+    }
+
+    if !held {
+        gamepad_focus.pressed = None;
+    }
+}
+
+/// Outlines the focused button in white.
+pub fn gamepad_focus_draw(
+    mut button_q: Query<(Entity, &mut BorderColor), With<Button>>,
+    gamepad_focus: Res<GamepadFocus>
+) {
+    if !gamepad_focus.is_changed() {
+        return;
+    }
+
+    for (entity, mut border_color) in &mut button_q {
+        let color = if gamepad_focus.button == Some(entity) { Color::WHITE } else { Color::ORANGE };
+
+        if border_color.0 != color {
+            border_color.0 = color;
+        }
+    }
+}
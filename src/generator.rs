@@ -0,0 +1,348 @@
+use std::{collections::HashMap, f32::consts::{PI, TAU}};
+
+use bevy::prelude::*;
+use rand::{distributions::WeightedIndex, prelude::*, rngs::StdRng};
+use serde::Deserialize;
+
+use crate::{atom::ATOM_SIZE, level_manager::LevelAtom};
+
+/// Closest two generated atoms may be, centre to centre, so they never overlap.
+pub const MIN_ATOM_SPACING: f32 = ATOM_SIZE * 2.;
+/// Candidates tried around each point before Poisson-disk sampling gives up on it.
+const POISSON_ATTEMPTS: usize = 30;
+
+/// Where generated atoms go. Every layout keeps its atoms at least `spacing` apart.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Layout {
+    /// A square grid clipped to a circle.
+    Grid { radius: f32, spacing: f32 },
+    /// A hexagonal lattice clipped to a circle, the densest way to pack atoms `spacing` apart.
+    Hex { radius: f32, spacing: f32 },
+    /// Random scatter filling a circle with no two atoms closer than `spacing`.
+    Poisson { radius: f32, spacing: f32 },
+    /// A sunflower spiral of `count` atoms growing outwards from the centre.
+    Spiral { count: usize, spacing: f32 },
+    /// Concentric rings `spacing` apart around a centre atom, each turned by a random amount.
+    Rings { rings: usize, spacing: f32 },
+    /// Clusters of scattered atoms `island_radius` across, spread around a circle with gaps between them.
+    Islands { islands: usize, island_radius: f32, radius: f32, spacing: f32 }
+}
+
+/// How many neutrons each generated atom releases.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum NeutronCounts {
+    Fixed(i32),
+    /// Any count from `min` to `max` inclusive, equally likely.
+    Uniform { min: i32, max: i32 },
+    /// Counts paired with their relative weights.
+    Weighted(Vec<(i32, f32)>)
+}
+
+/// A seeded layout of atoms, giving the same level every time for the same seed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Generator {
+    pub layout: Layout,
+    pub neutrons: NeutronCounts,
+    #[serde(default)]
+    pub seed: u64,
+    /// Moves the whole layout away from the origin.
+    #[serde(default)]
+    pub center: Vec2
+}
+
+impl Generator {
+    pub fn new(layout: Layout, neutrons: NeutronCounts, seed: u64) -> Self {
+        Generator { layout, neutrons, seed, center: Vec2::ZERO }
+    }
+
+    /// Generates the atoms, leaving out any that would be too close to `existing` ones.
+    pub fn generate(&self, existing: &[LevelAtom]) -> Vec<LevelAtom> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let spacing = self.layout.spacing();
+
+        let positions = self.layout.positions(&mut rng).into_iter().map(|position| position + self.center);
+        let positions = keep_spaced(positions, spacing, existing.iter().map(|atom| atom.position));
+
+        let counts = self.neutrons.sampler();
+
+        positions.into_iter()
+            .map(|position| LevelAtom::from((counts.sample(&mut rng), position)))
+            .collect()
+    }
+}
+
+impl Layout {
+    /// Spacing asked for, raised to the minimum if it is too tight.
+    pub fn spacing(&self) -> f32 {
+        let spacing = match self {
+            Layout::Grid { spacing, .. }
+            | Layout::Hex { spacing, .. }
+            | Layout::Poisson { spacing, .. }
+            | Layout::Spiral { spacing, .. }
+            | Layout::Rings { spacing, .. }
+            | Layout::Islands { spacing, .. } => *spacing
+        };
+
+        spacing.max(MIN_ATOM_SPACING)
+    }
+
+    /// How far from the layout's centre its atoms can reach.
+    pub fn extent(&self) -> f32 {
+        match *self {
+            Layout::Grid { radius, .. } | Layout::Hex { radius, .. } | Layout::Poisson { radius, .. } | Layout::Islands { radius, .. } => radius,
+            Layout::Spiral { count, .. } => self.spacing() * (count as f32).sqrt(),
+            Layout::Rings { rings, .. } => self.spacing() * rings as f32
+        }
+    }
+
+    fn positions(&self, rng: &mut StdRng) -> Vec<Vec2> {
+        let spacing = self.spacing();
+
+        match *self {
+            Layout::Grid { radius, .. } => grid(radius, Vec2::new(spacing, spacing), 0.),
+            Layout::Hex { radius, .. } => grid(radius, Vec2::new(spacing, spacing * 3_f32.sqrt() / 2.), spacing / 2.),
+            Layout::Poisson { radius, .. } => poisson_disk(Vec2::ZERO, radius, spacing, rng),
+            Layout::Spiral { count, .. } => {
+                let golden_angle = PI * (3. - 5_f32.sqrt());
+
+                (0..count).map(|i| Vec2::from_angle(i as f32 * golden_angle) * spacing * (i as f32).sqrt()).collect()
+            },
+            Layout::Rings { rings, .. } => {
+                let mut positions = vec![Vec2::ZERO];
+
+                for ring in 1..=rings {
+                    let radius = ring as f32 * spacing;
+                    // as many as fit with the straight line between neighbours at least `spacing`
+                    let count = (PI / (spacing / (2. * radius)).asin()).floor() as usize;
+                    let offset = rng.gen_range(0. ..TAU);
+
+                    positions.extend((0..count).map(|i| Vec2::from_angle(offset + TAU * i as f32 / count as f32) * radius));
+                }
+
+                positions
+            },
+            Layout::Islands { islands, island_radius, radius, .. } => {
+                // islands far enough apart that atoms on their edges are still `spacing` apart
+                let mut centers = poisson_disk(Vec2::ZERO, (radius - island_radius).max(0.), island_radius * 2. + spacing, rng);
+                centers.shuffle(rng);
+
+                centers.into_iter()
+                    .take(islands)
+                    .flat_map(|center| poisson_disk(center, island_radius, spacing, rng))
+                    .collect()
+            }
+        }
+    }
+}
+
+enum CountSampler {
+    Fixed(i32),
+    Uniform(i32, i32),
+    Weighted(Vec<i32>, WeightedIndex<f32>)
+}
+
+impl CountSampler {
+    fn sample(&self, rng: &mut StdRng) -> i32 {
+        match self {
+            CountSampler::Fixed(count) => *count,
+            CountSampler::Uniform(min, max) => rng.gen_range(*min..=*max),
+            CountSampler::Weighted(counts, weights) => counts[weights.sample(rng)]
+        }
+    }
+}
+
+impl NeutronCounts {
+    /// Falls back to one neutron per atom if the counts make no sense.
+    fn sampler(&self) -> CountSampler {
+        match self {
+            NeutronCounts::Fixed(count) => CountSampler::Fixed(*count),
+            NeutronCounts::Uniform { min, max } => CountSampler::Uniform(*min.min(max), *min.max(max)),
+            NeutronCounts::Weighted(counts) => match WeightedIndex::new(counts.iter().map(|(_, weight)| *weight)) {
+                Ok(weights) => CountSampler::Weighted(counts.iter().map(|(count, _)| *count).collect(), weights),
+                Err(error) => {
+                    error!("Invalid neutron count weights {:?}: {}", counts, error);
+                    CountSampler::Fixed(1)
+                }
+            }
+        }
+    }
+}
+
+/// Rows `step.y` apart of points `step.x` apart, every other row shifted by `row_offset`, inside a circle.
+fn grid(radius: f32, step: Vec2, row_offset: f32) -> Vec<Vec2> {
+    let mut positions = Vec::new();
+    let rows = (radius / step.y).floor() as i32;
+    let columns = (radius / step.x).ceil() as i32;
+
+    for row in -rows..=rows {
+        let offset = if row % 2 == 0 { 0. } else { row_offset };
+
+        for column in -columns..=columns {
+            let position = Vec2::new(column as f32 * step.x + offset, row as f32 * step.y);
+
+            if position.length_squared() <= radius.powi(2) {
+                positions.push(position);
+            }
+        }
+    }
+
+    positions
+}
+
+/// Bridson's Poisson-disk sampling inside a circle, starting from its centre.
+fn poisson_disk(center: Vec2, radius: f32, spacing: f32, rng: &mut StdRng) -> Vec<Vec2> {
+    let mut grid = SpacingGrid::new(spacing);
+    let mut positions = vec![center];
+    let mut active = vec![center];
+    grid.insert(center);
+
+    while !active.is_empty() {
+        let index = rng.gen_range(0..active.len());
+        let origin = active[index];
+
+        let candidate = (0..POISSON_ATTEMPTS)
+            .map(|_| origin + Vec2::from_angle(rng.gen_range(0. ..TAU)) * rng.gen_range(spacing..spacing * 2.))
+            .find(|candidate| candidate.distance_squared(center) <= radius.powi(2) && !grid.is_near(*candidate));
+
+        match candidate {
+            Some(candidate) => {
+                grid.insert(candidate);
+                positions.push(candidate);
+                active.push(candidate);
+            },
+            None => {
+                active.swap_remove(index);
+            }
+        }
+    }
+
+    positions
+}
+
+/// Drops every position closer than `spacing` to one before it or to any of the `existing` ones.
+fn keep_spaced(positions: impl IntoIterator<Item = Vec2>, spacing: f32, existing: impl IntoIterator<Item = Vec2>) -> Vec<Vec2> {
+    let mut grid = SpacingGrid::new(spacing);
+
+    for position in existing {
+        grid.insert(position);
+    }
+
+    positions.into_iter()
+        .filter(|position| {
+            if grid.is_near(*position) {
+                return false;
+            }
+
+            grid.insert(*position);
+            true
+        })
+        .collect()
+}
+
+/// Buckets points into cells `spacing` wide so finding close neighbours only looks at nearby cells.
+struct SpacingGrid {
+    spacing: f32,
+    cells: HashMap<IVec2, Vec<Vec2>>
+}
+
+impl SpacingGrid {
+    fn new(spacing: f32) -> Self {
+        SpacingGrid { spacing, cells: HashMap::new() }
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.spacing).floor().as_ivec2()
+    }
+
+    fn insert(&mut self, position: Vec2) {
+        self.cells.entry(self.cell(position)).or_default().push(position);
+    }
+
+    fn is_near(&self, position: Vec2) -> bool {
+        let cell = self.cell(position);
+
+        (-1..=1).flat_map(|x| (-1..=1).map(move |y| cell + IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            // a hair of slack so lattice neighbours exactly `spacing` apart aren't dropped
+            .any(|other| other.distance_squared(position) < (self.spacing * 0.999).powi(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{Generator, Layout, NeutronCounts, MIN_ATOM_SPACING};
+    use crate::level_manager::LevelAtom;
+
+    fn layouts() -> Vec<Layout> {
+        vec![
+            Layout::Grid { radius: 600., spacing: 75. },
+            Layout::Hex { radius: 600., spacing: 75. },
+            Layout::Poisson { radius: 600., spacing: 80. },
+            Layout::Spiral { count: 150, spacing: 40. },
+            Layout::Rings { rings: 6, spacing: 90. },
+            Layout::Islands { islands: 5, island_radius: 150., radius: 800., spacing: 70. }
+        ]
+    }
+
+    fn assert_spaced(atoms: &[LevelAtom], spacing: f32) {
+        for (i, a) in atoms.iter().enumerate() {
+            for b in &atoms[i + 1..] {
+                assert!(a.position.distance(b.position) >= spacing * 0.999, "{} and {} are closer than {spacing}", a.position, b.position);
+            }
+        }
+    }
+
+    #[test]
+    fn layouts_keep_their_spacing() {
+        for layout in layouts() {
+            let spacing = layout.spacing();
+            let atoms = Generator::new(layout.clone(), NeutronCounts::Fixed(2), 7).generate(&[]);
+
+            assert!(atoms.len() > 10, "{layout:?} only made {} atoms", atoms.len());
+            assert_spaced(&atoms, spacing);
+        }
+    }
+
+    #[test]
+    fn spacing_is_never_below_the_minimum() {
+        let atoms = Generator::new(Layout::Hex { radius: 300., spacing: 1. }, NeutronCounts::Fixed(1), 0).generate(&[]);
+
+        assert_spaced(&atoms, MIN_ATOM_SPACING);
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        for layout in layouts() {
+            let neutrons = NeutronCounts::Weighted(vec![(1, 1.), (2, 2.), (3, 1.)]);
+            let first = Generator::new(layout.clone(), neutrons.clone(), 42).generate(&[]);
+            let second = Generator::new(layout.clone(), neutrons, 42).generate(&[]);
+
+            assert_eq!(first.iter().map(|atom| (atom.num_neutrons, atom.position)).collect::<Vec<_>>(), second.iter().map(|atom| (atom.num_neutrons, atom.position)).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn avoids_existing_atoms() {
+        let existing = vec![LevelAtom::from((5, Vec2::new(10., 10.)))];
+        let atoms = Generator::new(Layout::Grid { radius: 300., spacing: 75. }, NeutronCounts::Fixed(2), 0).generate(&existing);
+
+        assert_spaced(&[existing, atoms].concat(), 75.);
+    }
+
+    #[test]
+    fn neutron_counts_come_from_the_distribution() {
+        let generator = Generator::new(Layout::Poisson { radius: 800., spacing: 70. }, NeutronCounts::Uniform { min: 2, max: 4 }, 3);
+        let atoms = generator.generate(&[]);
+
+        assert!(atoms.iter().all(|atom| (2..=4).contains(&atom.num_neutrons)));
+        for count in 2..=4 {
+            assert!(atoms.iter().any(|atom| atom.num_neutrons == count), "no atom released {count}");
+        }
+
+        let weighted = Generator::new(Layout::Poisson { radius: 800., spacing: 70. }, NeutronCounts::Weighted(vec![(1, 0.), (6, 1.)]), 3);
+        assert!(weighted.generate(&[]).iter().all(|atom| atom.num_neutrons == 6));
+    }
+}
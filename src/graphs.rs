@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{collision::CollisionEvent, hud::Hud, neutron::Neutron, GameState, GameStats, MainCamera, in_run, level_manager::LevelSetupSet};
+
+/// Simulated seconds between timeline samples.
+const SAMPLE_INTERVAL: f32 = 0.05;
+/// Fissions per second are counted over this many simulated seconds.
+const FISSION_RATE_WINDOW: f32 = 1.;
+/// Finished runs kept around to draw behind the current one.
+const MAX_PREVIOUS_RUNS: usize = 3;
+
+const GRAPH_SIZE: Vec2 = Vec2::new(300., 80.);
+const GRAPH_MARGIN: f32 = 10.;
+const GRAPH_COLORS: [Color; 3] = [Color::rgb(0.3, 0.3, 1.0), Color::ORANGE, Color::YELLOW];
+const GRAPH_TEXT_SIZE: f32 = 14.;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimelineSample {
+    pub time: f32,
+    pub active_neutrons: usize,
+    pub score: f64,
+    pub fission_rate: f32
+}
+
+/// Samples of the current run.
+#[derive(Resource, Debug, Default)]
+pub struct Timeline {
+    pub samples: Vec<TimelineSample>,
+    recent_fissions: VecDeque<f32>,
+    next_sample_time: f32
+}
+
+/// Timelines of the last few finished runs, newest last.
+#[derive(Resource, Debug, Default)]
+pub struct RunHistory {
+    pub runs: VecDeque<Vec<TimelineSample>>
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct GraphsOptions {
+    pub visible: bool
+}
+
+#[derive(Component)]
+pub struct GraphLabel {
+    graph: usize
+}
+
+impl TimelineSample {
+    fn value(&self, graph: usize) -> f64 {
+        match graph {
+            0 => self.active_neutrons as f64,
+            1 => self.score,
+            _ => self.fission_rate as f64
+        }
+    }
+}
+
+pub struct GraphsPlugin;
+
+impl Plugin for GraphsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Timeline>()
+            .init_resource::<RunHistory>()
+            .init_resource::<GraphsOptions>()
+            .add_systems(OnEnter(GameState::SETUP), graphs_setup.in_set(LevelSetupSet::Spawn))
+            .add_systems(OnTransition { from: GameState::SETUP, to: GameState::GAME }, timeline_setup)
+            .add_systems(OnEnter(GameState::RESULTS), archive_timeline)
+            .add_systems(Update, timeline_record.run_if(in_state(GameState::GAME)))
+            .add_systems(Update, (graphs_toggle, graphs_draw).run_if(in_run));
+    }
+}
+
+pub fn timeline_setup(
+    mut commands: Commands
+) {
+    commands.insert_resource(Timeline::default());
+}
+
+pub fn timeline_record(
+    mut events: EventReader<CollisionEvent>,
+    neutron_q: Query<(), With<Neutron>>,
+    game_stats: Res<GameStats>,
+    mut timeline: ResMut<Timeline>
+) {
+    let now = game_stats.simulation_time;
+
+    for _ in events.read() {
+        timeline.recent_fissions.push_back(now);
+    }
+
+    while timeline.recent_fissions.front().is_some_and(|time| *time < now - FISSION_RATE_WINDOW) {
+        timeline.recent_fissions.pop_front();
+    }
+
+    if now < timeline.next_sample_time {
+        return;
+    }
+
+    let sample = TimelineSample {
+        time: now,
+        active_neutrons: neutron_q.iter().count(),
+        score: game_stats.score,
+        fission_rate: timeline.recent_fissions.len() as f32 / FISSION_RATE_WINDOW
+    };
+
+    timeline.samples.push(sample);
+    timeline.next_sample_time = now + SAMPLE_INTERVAL;
+}
+
+pub fn archive_timeline(
+    timeline: Res<Timeline>,
+    mut run_history: ResMut<RunHistory>
+) {
+    if timeline.samples.is_empty() {
+        return;
+    }
+
+    run_history.runs.push_back(timeline.samples.clone());
+
+    if run_history.runs.len() > MAX_PREVIOUS_RUNS {
+        run_history.runs.pop_front();
+    }
+}
+
+pub fn graphs_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>
+) {
+    let font = asset_server.load("fonts/JetBrainsMono-Regular.ttf");
+
+    for (graph, color) in GRAPH_COLORS.iter().enumerate() {
+        commands.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle { font: font.clone(), font_size: GRAPH_TEXT_SIZE, color: *color }
+            ).with_style(
+                Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(GRAPH_MARGIN + 4.),
+                    bottom: Val::Px(graph_bottom(graph) + GRAPH_SIZE.y - GRAPH_TEXT_SIZE - 4.),
+                    ..default()
+                }
+            ),
+            Hud,
+            GraphLabel { graph }
+        ));
+    }
+}
+
+pub fn graphs_toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut graphs_options: ResMut<GraphsOptions>
+) {
+    if keyboard_input.just_pressed(KeyCode::G) {
+        graphs_options.visible = !graphs_options.visible;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn graphs_draw(
+    mut gizmos: Gizmos,
+    window_q: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut label_q: Query<(&mut Text, &mut Visibility, &GraphLabel)>,
+    graphs_options: Res<GraphsOptions>,
+    game_state: Res<State<GameState>>,
+    timeline: Res<Timeline>,
+    run_history: Res<RunHistory>
+) {
+    // always show the graphs on the results screen
+    let visible = graphs_options.visible || game_state.get().eq(&GameState::RESULTS);
+
+    for (mut label_text, mut label_visibility, graph_label) in &mut label_q {
+        *label_visibility = if visible { Visibility::Inherited } else { Visibility::Hidden };
+
+        let latest = timeline.samples.last().map_or(0., |sample| sample.value(graph_label.graph));
+        label_text.sections[0].value = match graph_label.graph {
+            0 => format!("Neutrons: {latest:.0}"),
+            1 => format!("Energy: {latest:.3e} J"),
+            _ => format!("Fissions/s: {latest:.0}")
+        };
+    }
+
+    if !visible {
+        return;
+    }
+
+    let window = window_q.single();
+    let (camera, camera_transform) = camera_q.single();
+
+    // every run shares one time axis so shots can be compared, a finished run is already the newest in the history
+    let mut runs: Vec<&Vec<TimelineSample>> = run_history.runs.iter().collect();
+
+    if game_state.get().ne(&GameState::RESULTS) {
+        runs.push(&timeline.samples);
+    }
+
+    let max_time = runs.iter().filter_map(|samples| samples.last()).map(|sample| sample.time).fold(f32::EPSILON, f32::max);
+
+    for (graph, color) in GRAPH_COLORS.iter().enumerate() {
+        let max_value = runs.iter().flat_map(|samples| samples.iter()).map(|sample| sample.value(graph)).fold(f64::EPSILON, f64::max);
+
+        // graph corners in viewport coordinates, y pointing down
+        let bottom_left = Vec2::new(GRAPH_MARGIN, window.height() - graph_bottom(graph));
+        let to_world = |point: Vec2| camera.viewport_to_world_2d(camera_transform, point);
+
+        let (Some(world_bottom_left), Some(world_top_right)) = (to_world(bottom_left), to_world(bottom_left + Vec2::new(GRAPH_SIZE.x, -GRAPH_SIZE.y))) else {
+            return;
+        };
+
+        gizmos.rect_2d((world_bottom_left + world_top_right) / 2., 0., world_top_right - world_bottom_left, Color::GRAY);
+
+        for (run, samples) in runs.iter().enumerate() {
+            let is_current = run == runs.len() - 1;
+            let run_color = if is_current { *color } else { color.with_a(0.25) };
+
+            gizmos.linestrip_2d(
+                samples.iter().map(|sample| {
+                    let fraction = Vec2::new(sample.time / max_time, (sample.value(graph) / max_value) as f32);
+                    world_bottom_left + (world_top_right - world_bottom_left) * fraction
+                }),
+                run_color
+            );
+        }
+    }
+}
+
+/// Distance from the bottom of the window to the bottom of a graph, in pixels.
+fn graph_bottom(graph: usize) -> f32 {
+    GRAPH_MARGIN + (GRAPH_COLORS.len() - 1 - graph) as f32 * (GRAPH_SIZE.y + GRAPH_MARGIN)
+}
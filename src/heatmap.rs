@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{collision::CollisionEvent, in_run, level_manager::LevelSetupSet, GameState};
+
+/// Side length of a heatmap cell in world units.
+const HEATMAP_CELL_SIZE: f32 = 200.;
+/// Above the background, below neutrons, absorbers and atoms.
+const HEATMAP_Z: f32 = 0.5;
+const HEATMAP_MIN_ALPHA: f32 = 0.15;
+const HEATMAP_MAX_ALPHA: f32 = 0.6;
+
+/// Energy released in each cell of the current run.
+#[derive(Resource, Debug, Default)]
+pub struct Heatmap {
+    pub cells: HashMap<IVec2, f64>,
+    pub max_energy: f64
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct HeatmapOptions {
+    pub visible: bool
+}
+
+#[derive(Component)]
+pub struct HeatmapCell {
+    cell: IVec2
+}
+
+impl Heatmap {
+    pub fn cell_at(position: Vec2) -> IVec2 {
+        (position / HEATMAP_CELL_SIZE).floor().as_ivec2()
+    }
+}
+
+/// Blue for the coolest cells through to red for the hottest.
+fn heat_color(heat: f32) -> Color {
+    Color::hsla(240. * (1. - heat), 1., 0.5, HEATMAP_MIN_ALPHA + (HEATMAP_MAX_ALPHA - HEATMAP_MIN_ALPHA) * heat)
+}
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmap>()
+            .init_resource::<HeatmapOptions>()
+            .add_systems(OnEnter(GameState::SETUP), heatmap_cleanup.in_set(LevelSetupSet::Cleanup))
+            .add_systems(Update, heatmap_record.run_if(in_state(GameState::GAME)))
+            .add_systems(Update, (heatmap_toggle, heatmap_update.after(heatmap_record)).run_if(in_run));
+    }
+}
+
+pub fn heatmap_record(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    mut heatmap: ResMut<Heatmap>
+) {
+    for collision_event in events.read() {
+        let cell = Heatmap::cell_at(collision_event.atom_position);
+
+        let energy = heatmap.cells.entry(cell).or_insert_with(|| {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite { custom_size: Some(Vec2::splat(HEATMAP_CELL_SIZE)), ..default() },
+                    transform: Transform {
+                        translation: Vec3::from(((cell.as_vec2() + 0.5) * HEATMAP_CELL_SIZE, HEATMAP_Z)),
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                HeatmapCell { cell }
+            ));
+
+            0.
+        });
+
+        *energy += collision_event.energy;
+        let energy = *energy;
+        heatmap.max_energy = heatmap.max_energy.max(energy);
+    }
+}
+
+pub fn heatmap_update(
+    mut cell_q: Query<(&HeatmapCell, &mut Sprite, &mut Visibility)>,
+    heatmap: Res<Heatmap>,
+    heatmap_options: Res<HeatmapOptions>
+) {
+    if !heatmap.is_changed() && !heatmap_options.is_changed() {
+        return;
+    }
+
+    for (heatmap_cell, mut sprite, mut visibility) in &mut cell_q {
+        let energy = heatmap.cells.get(&heatmap_cell.cell).copied().unwrap_or_default();
+
+        sprite.color = heat_color((energy / heatmap.max_energy.max(f64::EPSILON)) as f32);
+        *visibility = if heatmap_options.visible { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+pub fn heatmap_toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut heatmap_options: ResMut<HeatmapOptions>
+) {
+    if keyboard_input.just_pressed(KeyCode::H) {
+        heatmap_options.visible = !heatmap_options.visible;
+    }
+}
+
+pub fn heatmap_cleanup(
+    mut commands: Commands,
+    cell_q: Query<Entity, With<HeatmapCell>>
+) {
+    commands.insert_resource(Heatmap::default());
+    cell_q.for_each(|cell_entity| commands.entity(cell_entity).despawn());
+}
@@ -4,7 +4,7 @@
 
 use bevy::{prelude::*, diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin}};
 
-use crate::{GameStats, level_manager::{LevelStats, calculate_grade}, menu::{PRESSED_BUTTON, HOVERED_BUTTON, NORMAL_BUTTON}, GameState};
+use crate::{GameStats, level_manager::LevelStats, objective::Objectives, power::{PowerStats, ScoreMetric}, menu::{PRESSED_BUTTON, HOVERED_BUTTON, NORMAL_BUTTON}, player_controls::PlacementState, criticality::{Criticality, Regime}, trail::{TrailOptions, TrailColorMode}, input::{Action, InputBindings}, GameState, level_manager::LevelSetupSet, in_level, in_run, endless::{EndlessResult, EndlessSeed, EndlessScores}};
 
 #[derive(Component)]
 pub struct Hud;
@@ -15,7 +15,48 @@ pub struct FpsText;
 #[derive(Component)]
 pub struct GameStatsText;
 
+/// Lists the level's objectives, empty when it has none.
+#[derive(Component)]
+pub struct ObjectivesText;
+
+#[derive(Component)]
+pub struct ResetButton;
+
+#[derive(Component)]
+pub struct PausedText;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailButton {
+    Toggle,
+    ColorMode
+}
+
+#[derive(Component)]
+pub struct TrailButtonText(TrailButton);
+
 const HUD_TEXT_SIZE: f32 = 20.;
+/// Simulated seconds the results show the most power held for.
+const POWER_CURVE_DURATIONS: [f32; 3] = [1., 2., 5.];
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        // for the fps counter
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+
+        app.add_systems(OnEnter(GameState::SETUP), (hud_cleanup.in_set(LevelSetupSet::Cleanup), hud_setup.in_set(LevelSetupSet::Spawn)))
+            // only when a run starts, not every time it is unpaused
+            .add_systems(OnTransition { from: GameState::SETUP, to: GameState::GAME }, setup_reset_button)
+            .add_systems(OnEnter(GameState::RESULTS), results_setup)
+            .add_systems(OnEnter(GameState::PAUSED), paused_setup)
+            .add_systems(OnExit(GameState::PAUSED), paused_cleanup)
+            .add_systems(Update, reset_button.run_if(in_run))
+            .add_systems(Update, (hud_text_update, objectives_text_update, trail_buttons, trail_button_text_update).run_if(in_level));
+    }
+}
 
 pub fn hud_setup(
     mut commands: Commands,
@@ -29,8 +70,7 @@ pub fn hud_setup(
     let variable_text_style = TextStyle {
         font: static_text_style.font.clone(),
         font_size: HUD_TEXT_SIZE,
-        color: Color::ORANGE,
-        ..default()
+        color: Color::ORANGE
     };
 
     // Fps ui
@@ -54,11 +94,19 @@ pub fn hud_setup(
         TextBundle::from_sections([
             TextSection::new("Energy: ", static_text_style.clone()),
             TextSection::from_style(variable_text_style.clone()),
-            TextSection::new("\nEnergy Grade: ", static_text_style.clone()),
+            TextSection::new("\nGrade: ", static_text_style.clone()),
+            TextSection::from_style(variable_text_style.clone()),
+            TextSection::new("\nNext Grade: ", static_text_style.clone()),
             TextSection::from_style(variable_text_style.clone()),
             TextSection::new("\nRemaining Neutrons: ", static_text_style.clone()),
             TextSection::from_style(variable_text_style.clone()),
             TextSection::new("\nSimulation Speed: ", static_text_style.clone()),
+            TextSection::from_style(variable_text_style.clone()),
+            TextSection::new("\nRemaining Control Rods: ", static_text_style.clone()),
+            TextSection::from_style(variable_text_style.clone()),
+            TextSection::new("\nPlacing (R to switch): ", static_text_style.clone()),
+            TextSection::from_style(variable_text_style.clone()),
+            TextSection::new("\nMultiplication Factor (X to export): ", static_text_style.clone()),
             TextSection::from_style(variable_text_style.clone())
         ]).with_style(
             Style {
@@ -71,6 +119,43 @@ pub fn hud_setup(
         Hud,
         GameStatsText
     ));
+
+    commands.spawn((
+        TextBundle::from_section("", static_text_style.clone()).with_style(
+            Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(210.),
+                left: Val::Px(5.),
+                ..default()
+            }
+        ),
+        Hud,
+        ObjectivesText
+    ));
+
+    for (trail_button, right, width) in [(TrailButton::Toggle, 85., 125.), (TrailButton::ColorMode, 215., 165.)] {
+        commands.spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(right),
+                    bottom: Val::Px(5.),
+                    width: Val::Px(width),
+                    height: Val::Px(50.),
+                    border: UiRect::all(Val::Px(2.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: Color::ORANGE.into(),
+                ..default()
+            },
+            Hud,
+            trail_button
+        )).with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", variable_text_style.clone()), TrailButtonText(trail_button)));
+        });
+    }
 }
 
 pub fn setup_reset_button(
@@ -81,8 +166,7 @@ pub fn setup_reset_button(
     let variable_text_style = TextStyle {
         font: asset_server.load("fonts/JetBrainsMono-Regular.ttf"),
         font_size: HUD_TEXT_SIZE,
-        color: Color::ORANGE,
-        ..default()
+        color: Color::ORANGE
     };
 
     commands.spawn((
@@ -101,12 +185,132 @@ pub fn setup_reset_button(
             border_color: Color::ORANGE.into(),
             ..default()
         },
-        Hud
+        Hud,
+        ResetButton
     )).with_children(|parent| {
         parent.spawn(TextBundle::from_section("Reset", variable_text_style));
     });
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn results_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_stats: Res<GameStats>,
+    level_stats: Res<LevelStats>,
+    objectives: Res<Objectives>,
+    power_stats: Res<PowerStats>,
+    endless_seed: Option<Res<EndlessSeed>>,
+    endless_scores: Res<EndlessScores>,
+    endless_result: Res<EndlessResult>
+) {
+    let static_text_style = TextStyle {
+        font: asset_server.load("fonts/JetBrainsMono-Regular.ttf"),
+        font_size: HUD_TEXT_SIZE,
+        ..default()
+    };
+    let variable_text_style = TextStyle {
+        font: static_text_style.font.clone(),
+        font_size: HUD_TEXT_SIZE,
+        color: Color::ORANGE
+    };
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Px(5.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        Hud
+    )).with_children(|parent| {
+        parent.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(10.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            border_color: Color::ORANGE.into(),
+            background_color: Color::BLACK.with_a(0.8).into(),
+            ..default()
+        }).with_children(|parent| {
+            let metric = level_stats.metric;
+            let graded = metric.value(&game_stats, &power_stats);
+            let power_curve = power_stats.power_curve(&POWER_CURVE_DURATIONS).iter()
+                .map(|(seconds, power)| format!("{power:.3e} W over {seconds} s"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            parent.spawn(TextBundle::from_sections([
+                TextSection::new("Reaction over!", variable_text_style.clone()),
+                TextSection::new("\nEnergy: ", static_text_style.clone()),
+                TextSection::new(format!("{:.12} J", game_stats.score), variable_text_style.clone()),
+                TextSection::new("\nPeak Power: ", static_text_style.clone()),
+                TextSection::new(format!("{:.3e} W", power_stats.peak_power), variable_text_style.clone()),
+                TextSection::new("\nPower Curve: ", static_text_style.clone()),
+                TextSection::new(power_curve, variable_text_style.clone()),
+                TextSection::new("\nGrade: ", static_text_style.clone()),
+                TextSection::new(graded_text(objectives.grade(graded, level_stats.as_ref()), metric, graded), variable_text_style.clone()),
+                TextSection::new("\nDuration: ", static_text_style.clone()),
+                TextSection::new(format!("{:.2} s", game_stats.simulation_time), variable_text_style.clone()),
+                TextSection::new("\nPress Reset to try another shot.", static_text_style.clone())
+            ]));
+
+            if !objectives.objectives.is_empty() {
+                parent.spawn(TextBundle::from_section(objectives_summary(&objectives), static_text_style.clone()));
+            }
+
+            // recorded just before this
+            if let Some(endless_seed) = &endless_seed {
+                let best = endless_scores.best(endless_seed.seed).unwrap_or(game_stats.score);
+                let new_best = if endless_result.new_best { " (new best!)" } else { "" };
+
+                parent.spawn(TextBundle::from_sections([
+                    TextSection::new(format!("{} best: ", endless_seed.label()), static_text_style.clone()),
+                    TextSection::new(format!("{:.12} J{}", best, new_best), variable_text_style.clone())
+                ]));
+            }
+        });
+    });
+}
+
+pub fn paused_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    input_bindings: Res<InputBindings>
+) {
+    let pause_binding = input_bindings.get(Action::Pause).map(|binding| binding.to_string()).unwrap_or_default();
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("Paused", TextStyle { font: asset_server.load("fonts/JetBrainsMono-Regular.ttf"), font_size: 48., color: Color::ORANGE }),
+            TextSection::new(format!("\n{pause_binding} to resume"), TextStyle { font: asset_server.load("fonts/JetBrainsMono-Regular.ttf"), font_size: HUD_TEXT_SIZE, ..default() })
+        ]).with_text_alignment(TextAlignment::Center).with_style(
+            Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            }
+        ),
+        Hud,
+        PausedText
+    ));
+}
+
+pub fn paused_cleanup(
+    mut commands: Commands,
+    paused_q: Query<Entity, With<PausedText>>
+) {
+    paused_q.for_each(|paused_entity| commands.entity(paused_entity).despawn_recursive());
+}
+
 pub fn hud_cleanup(
     mut commands: Commands,
     hud_q: Query<Entity, With<Hud>>
@@ -114,12 +318,17 @@ pub fn hud_cleanup(
     hud_q.for_each(|hud_entity| commands.entity(hud_entity).despawn_recursive());
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn hud_text_update(
     diagnostics: Res<DiagnosticsStore>,
     mut fps_text_q: Query<&mut Text, (With<FpsText>, Without<GameStatsText>)>,
     mut game_stats_text_q: Query<&mut Text, (With<GameStatsText>, Without<FpsText>)>,
     game_stats: Res<GameStats>,
-    level_stats: Res<LevelStats>
+    level_stats: Res<LevelStats>,
+    objectives: Res<Objectives>,
+    power_stats: Res<PowerStats>,
+    placement_state: Res<State<PlacementState>>,
+    criticality: Res<Criticality>
 ) {
     let mut fps_text = fps_text_q.single_mut();
     let mut game_stats_text = game_stats_text_q.single_mut();
@@ -130,14 +339,59 @@ pub fn hud_text_update(
         }
     }
 
-    game_stats_text.sections[1].value = format!("{:.12} J", game_stats.score);
-    game_stats_text.sections[3].value = format!("{}", calculate_grade(game_stats.score, level_stats.as_ref()));
-    game_stats_text.sections[5].value = format!("{}", level_stats.num_neutrons);
-    game_stats_text.sections[7].value = format!("{:.2}", game_stats.simulation_speed);
+    let metric = level_stats.metric;
+    let graded = metric.value(&game_stats, &power_stats);
+
+    game_stats_text.sections[1].value = format!("{:.12} J ({:.3e} W)", game_stats.score, power_stats.power);
+    game_stats_text.sections[3].value = graded_text(objectives.grade(graded, level_stats.as_ref()), metric, graded);
+    game_stats_text.sections[5].value = match level_stats.next_grade(graded) {
+        Some((letter, needed)) => format!("{letter} in {needed:.3e} {}", metric.unit()),
+        None => "-".to_string()
+    };
+    game_stats_text.sections[7].value = format!("{}", level_stats.num_neutrons);
+    game_stats_text.sections[9].value = format!("{:.2}", game_stats.simulation_speed);
+    game_stats_text.sections[11].value = format!("{}", level_stats.num_control_rods);
+    game_stats_text.sections[13].value = match placement_state.get() {
+        PlacementState::ROD => "Control Rods",
+        _ => "Neutrons"
+    }.to_string();
+    game_stats_text.sections[15].value = match criticality.latest_multiplication_factor() {
+        Some((generation, k)) => format!("{k:.2} (gen {generation}, {:?})", Regime::from_multiplication_factor(k)),
+        None => "-".to_string()
+    };
 }
 
+pub fn objectives_text_update(
+    objectives: Res<Objectives>,
+    mut objectives_text_q: Query<&mut Text, With<ObjectivesText>>
+) {
+    if !objectives.is_changed() {
+        return;
+    }
+
+    for mut text in &mut objectives_text_q {
+        text.sections[0].value = objectives_summary(&objectives);
+    }
+}
+
+/// The grade, along with what it was graded on when that isn't the energy shown above it.
+fn graded_text(grade: &str, metric: ScoreMetric, graded: f64) -> String {
+    match metric {
+        ScoreMetric::Energy => grade.to_string(),
+        _ => format!("{grade} ({}: {graded:.3e} {})", metric.label(), metric.unit())
+    }
+}
+
+fn objectives_summary(objectives: &Objectives) -> String {
+    objectives.objectives.iter()
+        .map(|(objective, status)| format!("{} {}", status.symbol(), objective.description()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[allow(clippy::type_complexity)]
 pub fn reset_button(
-    mut interaction_q: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>, With<Hud>)>,
+    mut interaction_q: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>, With<ResetButton>)>,
     mut next_state: ResMut<NextState<GameState>>
 ) {
     for (interaction, mut bg_color) in &mut interaction_q {
@@ -154,4 +408,44 @@ pub fn reset_button(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[allow(clippy::type_complexity)]
+pub fn trail_buttons(
+    mut interaction_q: Query<(&Interaction, &mut BackgroundColor, &TrailButton), (Changed<Interaction>, With<Button>)>,
+    mut trail_options: ResMut<TrailOptions>
+) {
+    for (interaction, mut bg_color, trail_button) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *bg_color = PRESSED_BUTTON.into();
+
+                match trail_button {
+                    TrailButton::Toggle => trail_options.enabled = !trail_options.enabled,
+                    TrailButton::ColorMode => trail_options.color_mode = trail_options.color_mode.next()
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = HOVERED_BUTTON.into();
+            },
+            Interaction::None => {
+                *bg_color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn trail_button_text_update(
+    mut text_q: Query<(&mut Text, &TrailButtonText)>,
+    trail_options: Res<TrailOptions>
+) {
+    for (mut text, trail_button_text) in &mut text_q {
+        text.sections[0].value = match trail_button_text.0 {
+            TrailButton::Toggle => format!("Trails: {}", if trail_options.enabled { "On" } else { "Off" }),
+            TrailButton::ColorMode => format!("Color: {}", match trail_options.color_mode {
+                TrailColorMode::Generation => "Gen",
+                TrailColorMode::Speed => "Speed"
+            })
+        };
+    }
+}
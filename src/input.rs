@@ -0,0 +1,440 @@
+use std::{collections::BTreeMap, fmt, fs, io};
+
+use bevy::{prelude::*, ecs::system::SystemParam};
+use serde::{Deserialize, Serialize};
+
+use crate::{menu::{PRESSED_BUTTON, HOVERED_BUTTON, NORMAL_BUTTON}, GameState, MainCamera};
+
+const BINDINGS_PATH: &str = "bindings.ron";
+const CONTROLS_TEXT_SIZE: f32 = 28.;
+/// Keys with fixed jobs that actions can't be bound to: reset, fit the level, control rod mode, export criticality,
+/// graphs, effects, heatmap, the precision form and cancelling a rebind.
+pub const RESERVED_KEYS: [KeyCode; 9] = [KeyCode::R, KeyCode::F, KeyCode::C, KeyCode::X, KeyCode::G, KeyCode::V, KeyCode::H, KeyCode::Tab, KeyCode::Escape];
+
+/// Everything the player can do that can be rebound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    Place,
+    Remove,
+    Start,
+    Pause,
+    SpeedUp,
+    SpeedDown
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton)
+}
+
+/// The key or button behind each action, loaded from `bindings.ron` at startup.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct InputBindings {
+    bindings: BTreeMap<Action, Binding>
+}
+
+/// Reads actions through the current bindings and any gamepad, use in place of `Input<KeyCode>`/`Input<MouseButton>`.
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    mouse_button_input: Res<'w, Input<MouseButton>>,
+    gamepad_button_input: Res<'w, Input<GamepadButton>>,
+    gamepads: Res<'w, Gamepads>,
+    input_bindings: Res<'w, InputBindings>
+}
+
+/// A cursor steered with a gamepad, which stands in for the mouse while it is active.
+#[derive(Resource, Debug, Default)]
+pub struct VirtualCursor {
+    /// In window coordinates, like `Window::cursor_position`.
+    pub position: Vec2,
+    pub active: bool
+}
+
+/// Where the player is pointing, with the mouse or the gamepad's virtual cursor.
+#[derive(SystemParam)]
+pub struct Pointer<'w, 's> {
+    window_q: Query<'w, 's, &'static Window>,
+    camera_q: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>,
+    virtual_cursor: Res<'w, VirtualCursor>
+}
+
+/// The action waiting for the player to press its new key, if any.
+#[derive(Resource, Debug, Default)]
+pub struct Rebinding {
+    action: Option<Action>,
+    /// The last key pressed for it that was reserved.
+    refused: Option<Binding>
+}
+
+#[derive(Component)]
+pub struct ControlsMenu;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlsButton {
+    Rebind(Action),
+    ResetDefaults,
+    Back
+}
+
+#[derive(Component)]
+pub struct BindingText(Action);
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::PanUp, Action::PanDown, Action::PanLeft, Action::PanRight,
+        Action::ZoomIn, Action::ZoomOut,
+        Action::Place, Action::Remove,
+        Action::Start, Action::Pause,
+        Action::SpeedUp, Action::SpeedDown
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Action::PanUp => "Pan Up",
+            Action::PanDown => "Pan Down",
+            Action::PanLeft => "Pan Left",
+            Action::PanRight => "Pan Right",
+            Action::ZoomIn => "Zoom In",
+            Action::ZoomOut => "Zoom Out",
+            Action::Place => "Place",
+            Action::Remove => "Remove",
+            Action::Start => "Start Reaction",
+            Action::Pause => "Pause",
+            Action::SpeedUp => "Speed Up",
+            Action::SpeedDown => "Slow Down"
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key_code) => write!(f, "{key_code:?}"),
+            Binding::Mouse(mouse_button) => write!(f, "Mouse {mouse_button:?}")
+        }
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings {
+            bindings: BTreeMap::from([
+                (Action::PanUp, Binding::Key(KeyCode::W)),
+                (Action::PanDown, Binding::Key(KeyCode::S)),
+                (Action::PanLeft, Binding::Key(KeyCode::A)),
+                (Action::PanRight, Binding::Key(KeyCode::D)),
+                (Action::ZoomIn, Binding::Key(KeyCode::E)),
+                (Action::ZoomOut, Binding::Key(KeyCode::Q)),
+                (Action::Place, Binding::Mouse(MouseButton::Left)),
+                (Action::Remove, Binding::Mouse(MouseButton::Right)),
+                (Action::Start, Binding::Key(KeyCode::Space)),
+                (Action::Pause, Binding::Key(KeyCode::P)),
+                (Action::SpeedUp, Binding::Key(KeyCode::Equals)),
+                (Action::SpeedDown, Binding::Key(KeyCode::Minus))
+            ])
+        }
+    }
+}
+
+impl InputBindings {
+    /// Reads the bindings file, any action it leaves out keeps its default binding.
+    pub fn load() -> Self {
+        let mut input_bindings = InputBindings::default();
+
+        match fs::read_to_string(BINDINGS_PATH) {
+            Ok(contents) => match ron::from_str::<InputBindings>(&contents) {
+                Ok(loaded) => input_bindings.bindings.extend(loaded.bindings),
+                Err(error) => error!("Failed to parse {}, using default bindings: {}", BINDINGS_PATH, error)
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => error!("Failed to read {}, using default bindings: {}", BINDINGS_PATH, error)
+        }
+
+        input_bindings
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        fs::write(BINDINGS_PATH, contents)
+    }
+
+    pub fn get(&self, action: Action) -> Option<Binding> {
+        self.bindings.get(&action).copied()
+    }
+
+    /// Binds `action`, giving its old binding to whichever action had this one so no two share a key.
+    /// Returns that action, or an error for a reserved key.
+    pub fn bind(&mut self, action: Action, binding: Binding) -> Result<Option<Action>, String> {
+        if matches!(binding, Binding::Key(key_code) if RESERVED_KEYS.contains(&key_code)) {
+            return Err(format!("{binding} is reserved"));
+        }
+
+        let previous = self.bindings.insert(action, binding);
+        let swapped = self.bindings.iter().find(|(other, other_binding)| **other != action && **other_binding == binding).map(|(other, _)| *other);
+
+        if let Some(other) = swapped {
+            match previous {
+                Some(previous) => self.bindings.insert(other, previous),
+                None => self.bindings.remove(&other)
+            };
+        }
+
+        Ok(swapped)
+    }
+}
+
+impl Actions<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        let bound_pressed = match self.input_bindings.get(action) {
+            Some(Binding::Key(key_code)) => self.keyboard_input.pressed(key_code),
+            Some(Binding::Mouse(mouse_button)) => self.mouse_button_input.pressed(mouse_button),
+            None => false
+        };
+
+        bound_pressed || self.gamepad_buttons(action).any(|gamepad_button| self.gamepad_button_input.pressed(gamepad_button))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        let bound_pressed = match self.input_bindings.get(action) {
+            Some(Binding::Key(key_code)) => self.keyboard_input.just_pressed(key_code),
+            Some(Binding::Mouse(mouse_button)) => self.mouse_button_input.just_pressed(mouse_button),
+            None => false
+        };
+
+        bound_pressed || self.gamepad_buttons(action).any(|gamepad_button| self.gamepad_button_input.just_pressed(gamepad_button))
+    }
+
+    /// The action's button on every connected gamepad. Sticks and triggers are handled in `gamepad`.
+    fn gamepad_buttons(&self, action: Action) -> impl Iterator<Item = GamepadButton> + '_ {
+        let button_type = match action {
+            Action::Place => Some(GamepadButtonType::South),
+            Action::Remove => Some(GamepadButtonType::East),
+            Action::Start => Some(GamepadButtonType::North),
+            Action::Pause => Some(GamepadButtonType::Start),
+            Action::SpeedUp => Some(GamepadButtonType::RightTrigger),
+            Action::SpeedDown => Some(GamepadButtonType::LeftTrigger),
+            _ => None
+        };
+
+        self.gamepads.iter().filter_map(move |gamepad| button_type.map(|button_type| GamepadButton::new(gamepad, button_type)))
+    }
+}
+
+impl Pointer<'_, '_> {
+    pub fn screen_position(&self) -> Option<Vec2> {
+        if self.virtual_cursor.active {
+            Some(self.virtual_cursor.position)
+        } else {
+            self.window_q.single().cursor_position()
+        }
+    }
+
+    pub fn world_position(&self) -> Option<Vec2> {
+        let (camera, camera_transform) = self.camera_q.single();
+        self.screen_position().and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    }
+}
+
+/// Rebindable actions, the virtual cursor they can drive, and the controls screen for rebinding them.
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputBindings::load())
+            .init_resource::<Rebinding>()
+            .init_resource::<VirtualCursor>()
+            .add_systems(OnEnter(GameState::CONTROLS), controls_menu_setup)
+            .add_systems(OnExit(GameState::CONTROLS), controls_menu_cleanup)
+            .add_systems(Update, (controls_capture, controls_buttons).chain().run_if(in_state(GameState::CONTROLS)));
+    }
+}
+
+pub fn controls_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    input_bindings: Res<InputBindings>
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/JetBrainsMono-Regular.ttf"),
+        font_size: CONTROLS_TEXT_SIZE,
+        color: Color::ORANGE
+    };
+
+    let button_style = Style {
+        width: Val::Px(260.),
+        height: Val::Px(40.),
+        border: UiRect::all(Val::Px(3.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let root_node = NodeBundle {
+        style: Style {
+            left: Val::Percent(5.),
+            top: Val::Percent(5.),
+            width: Val::Percent(90.),
+            height: Val::Percent(90.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.),
+            border: UiRect::all(Val::Px(5.)),
+            ..default()
+        },
+        border_color: Color::ORANGE.into(),
+        ..default()
+    };
+
+    commands.spawn((root_node, ControlsMenu))
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Controls", TextStyle { font_size: 56., ..text_style.clone() }));
+
+        for action in Action::ALL {
+            parent.spawn(NodeBundle {
+                style: Style { align_items: AlignItems::Center, column_gap: Val::Px(16.), ..default() },
+                ..default()
+            }).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(action.label(), text_style.clone()).with_style(Style { width: Val::Px(300.), ..default() }));
+
+                parent.spawn((
+                    ButtonBundle { style: button_style.clone(), border_color: Color::ORANGE.into(), ..default() },
+                    ControlsButton::Rebind(action)
+                )).with_children(|parent| {
+                    let binding = input_bindings.get(action).map(|binding| binding.to_string()).unwrap_or_default();
+                    parent.spawn((TextBundle::from_section(binding, text_style.clone()), BindingText(action)));
+                });
+            });
+        }
+
+        parent.spawn(NodeBundle {
+            style: Style { column_gap: Val::Px(16.), margin: UiRect::top(Val::Px(12.)), ..default() },
+            ..default()
+        }).with_children(|parent| {
+            for (controls_button, label) in [(ControlsButton::ResetDefaults, "Defaults"), (ControlsButton::Back, "Back")] {
+                parent.spawn((
+                    ButtonBundle { style: button_style.clone(), border_color: Color::ORANGE.into(), ..default() },
+                    controls_button
+                )).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                });
+            }
+        });
+    });
+}
+
+pub fn controls_menu_cleanup(
+    mut commands: Commands,
+    controls_menu_q: Query<Entity, With<ControlsMenu>>,
+    input_bindings: Res<InputBindings>,
+    mut rebinding: ResMut<Rebinding>
+) {
+    controls_menu_q.for_each(|controls_menu_entity| commands.entity(controls_menu_entity).despawn_recursive());
+    *rebinding = Rebinding::default();
+
+    match input_bindings.save() {
+        Ok(()) => info!("Saved bindings to {}", BINDINGS_PATH),
+        Err(error) => error!("Failed to save bindings to {}: {}", BINDINGS_PATH, error)
+    }
+}
+
+/// Binds the action being rebound to the next key or mouse button pressed, escape cancels.
+pub fn controls_capture(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut input_bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<Rebinding>
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebinding.action = None;
+        rebinding.refused = None;
+        return;
+    }
+
+    let binding = keyboard_input.get_just_pressed().next().map(|key_code| Binding::Key(*key_code))
+        .or_else(|| mouse_button_input.get_just_pressed().next().map(|mouse_button| Binding::Mouse(*mouse_button)));
+
+    let Some(binding) = binding else {
+        return;
+    };
+
+    // a reserved key leaves it waiting for another
+    match input_bindings.bind(action, binding) {
+        Ok(swapped) => {
+            rebinding.action = None;
+            rebinding.refused = None;
+
+            debug!("Bound {:?} to {}", action, binding);
+
+            if let Some(swapped) = swapped {
+                debug!("Swapped {:?} to {:?}'s old binding", swapped, action);
+            }
+        },
+        Err(error) => {
+            rebinding.refused = Some(binding);
+            debug!("Can't bind {:?}: {}", action, error);
+        }
+    }
+}
+
+pub fn controls_buttons(
+    mut interaction_q: Query<(&Interaction, &mut BackgroundColor, &ControlsButton), Changed<Interaction>>,
+    mut binding_text_q: Query<(&mut Text, &BindingText)>,
+    mut input_bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    // a click that was just captured as a binding shouldn't also press the button under the cursor
+    let captured = rebinding.is_changed();
+
+    for (interaction, mut bg_color, controls_button) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *bg_color = PRESSED_BUTTON.into();
+
+                if captured {
+                    continue;
+                }
+
+                match controls_button {
+                    ControlsButton::Rebind(action) => *rebinding = Rebinding { action: Some(*action), refused: None },
+                    ControlsButton::ResetDefaults => *input_bindings = InputBindings::default(),
+                    ControlsButton::Back => next_state.set(GameState::SETTINGS)
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = HOVERED_BUTTON.into();
+            },
+            Interaction::None => {
+                *bg_color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+
+    if input_bindings.is_changed() || rebinding.is_changed() {
+        for (mut text, binding_text) in &mut binding_text_q {
+            text.sections[0].value = if rebinding.action == Some(binding_text.0) {
+                match rebinding.refused {
+                    Some(refused) => format!("{refused} is taken..."),
+                    None => "Press a key...".to_string()
+                }
+            } else {
+                input_bindings.get(binding_text.0).map(|binding| binding.to_string()).unwrap_or_default()
+            };
+        }
+    }
+}
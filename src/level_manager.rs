@@ -1,47 +1,204 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::{atom::*, GameStats};
+use crate::{atom::*, generator::Generator, absorber::{AbsorberShape, spawn_absorber}, wall::{Wall, Boundary, spawn_wall}, neutron::{SplitPattern, SplitShape}, fragment::DelayedEmission, settings::Settings, objective::{AtomTag, Objective, Objectives}, power::ScoreMetric, GameStats, absorber::{absorber_cleanup, absorber_collision}, wall::wall_cleanup, GameState};
 
-#[derive(Component)]
+/// Everything a level is made of. Besides the built in levels, one can be loaded from a RON file with `--level-file`.
+#[derive(Component, Debug, Clone, Deserialize)]
 pub struct Level {
     //level: i32,
-    atoms: Vec<(i32, Vec2)>,
-    level_stats: LevelStats
+    #[serde(default)]
+    pub atoms: Vec<LevelAtom>,
+    /// Seeded layouts adding more atoms, kept clear of the ones above.
+    #[serde(default)]
+    pub generators: Vec<Generator>,
+    #[serde(default)]
+    pub absorbers: Vec<(AbsorberShape, Vec2)>,
+    #[serde(default)]
+    pub walls: Vec<Wall>,
+    #[serde(default)]
+    pub boundary: Option<Boundary>,
+    /// Split pattern for every atom with the given number of neutrons, unless the atom sets its own.
+    #[serde(default)]
+    pub split_patterns: Vec<(i32, SplitPattern)>,
+    /// Goals on top of the energy target, all of which have to be met.
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    pub level_stats: LevelStats
+}
+
+impl Level {
+    /// The level's own atoms followed by every generator's, in order.
+    pub fn all_atoms(&self) -> Vec<LevelAtom> {
+        self.generators.iter().fold(self.atoms.clone(), |mut atoms, generator| {
+            let generated = generator.generate(&atoms);
+            atoms.extend(generated);
+            atoms
+        })
+    }
+}
+
+/// A level loaded from a file on the command line, played instead of the built in level.
+#[derive(Resource, Debug, Clone)]
+pub struct LevelFile(pub Level);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LevelAtom {
+    pub num_neutrons: i32,
+    pub position: Vec2,
+    #[serde(default)]
+    pub split_pattern: Option<SplitPattern>,
+    #[serde(default)]
+    pub delayed_emission: Option<DelayedEmission>,
+    #[serde(default)]
+    pub tag: Option<AtomTag>
 }
 
-#[derive(Resource, Debug)]
+impl From<(i32, Vec2)> for LevelAtom {
+    fn from((num_neutrons, position): (i32, Vec2)) -> Self {
+        LevelAtom { num_neutrons, position, split_pattern: None, delayed_emission: None, tag: None }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Deserialize)]
 pub struct LevelStats {
     pub num_neutrons: i32,
-    pub s_score: f64
+    pub num_control_rods: i32,
+    pub s_score: f64,
+    /// The level's own grades from best to worst, in place of the usual S to D curve. Anything below the last is an F.
+    #[serde(default)]
+    pub grades: Vec<GradeThreshold>,
+    /// What the grade is based on, energy unless the level says otherwise.
+    #[serde(default)]
+    pub metric: ScoreMetric
+}
+
+/// The least of the level's metric that earns a grade.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GradeThreshold {
+    pub letter: String,
+    pub at: Threshold
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Threshold {
+    /// In the units of the level's metric, joules unless it grades on power.
+    Value(f64),
+    /// Percent of `s_score`, so 100 is the S score and above that makes room for an S+.
+    Percent(f64)
+}
+
+impl Threshold {
+    pub fn value(&self, s_score: f64) -> f64 {
+        match *self {
+            Threshold::Value(value) => value,
+            Threshold::Percent(percent) => s_score * percent / 100.
+        }
+    }
+}
+
+impl LevelStats {
+    /// Graded on energy with the usual S to D curve.
+    pub fn new(num_neutrons: i32, num_control_rods: i32, s_score: f64) -> Self {
+        LevelStats { num_neutrons, num_control_rods, s_score, grades: vec![], metric: ScoreMetric::Energy }
+    }
+
+    /// Every grade with the score it needs, best first.
+    pub fn grade_thresholds(&self) -> Vec<(&str, f64)> {
+        let mut thresholds: Vec<(&str, f64)> = if self.grades.is_empty() {
+            // each letter i needs half the S score plus 1/(2 + i) of it
+            ["S", "A", "B", "C", "D"].into_iter()
+                .enumerate()
+                .map(|(i, letter)| (letter, self.s_score * 0.5 + self.s_score * 1./(2. + i as f64)))
+                .collect()
+        } else {
+            self.grades.iter().map(|grade| (grade.letter.as_str(), grade.at.value(self.s_score))).collect()
+        };
+
+        thresholds.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        thresholds
+    }
+
+    /// The next grade up and how much more of the level's metric it needs, or `None` at the top grade.
+    pub fn next_grade(&self, score: f64) -> Option<(&str, f64)> {
+        self.grade_thresholds().into_iter()
+            .rev()
+            .find(|(_, needed)| score + f64::EPSILON < *needed)
+            .map(|(letter, needed)| (letter, needed - score))
+    }
+}
+
+/// The steps of setting up a level, run in order every time `GameState::SETUP` is entered.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LevelSetupSet {
+    /// Clear out whatever the last run left behind, whether it was reset mid reaction or from the results.
+    Cleanup,
+    /// Spawn the level and everything on screen with it.
+    Spawn,
+    /// Anything that needs the level in place, like framing the cameras around it. The spawns are applied before it runs.
+    Frame
+}
+
+/// Loads levels along with their absorbers and walls.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(OnEnter(GameState::SETUP), (LevelSetupSet::Cleanup, LevelSetupSet::Spawn, LevelSetupSet::Frame).chain())
+            .add_systems(OnEnter(GameState::SETUP), ((absorber_cleanup, wall_cleanup).in_set(LevelSetupSet::Cleanup), setup_level.in_set(LevelSetupSet::Spawn)))
+            // commands are only applied at the end of the schedule otherwise
+            .add_systems(OnEnter(GameState::SETUP), apply_deferred.after(LevelSetupSet::Spawn).before(LevelSetupSet::Frame))
+            .add_systems(Update, absorber_collision.run_if(in_state(GameState::GAME)));
+    }
 }
 
 pub fn setup_level(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut game_stats: ResMut<GameStats>,
+    settings: Res<Settings>,
+    level_file: Option<Res<LevelFile>>,
     mut meshes: ResMut<Assets<Mesh>>, 
     mut materials: ResMut<Assets<ColorMaterial>>
 ) {
-    if let Some(level) = get_level(game_stats.level) {
-        for (atom_neutrons, position) in level.atoms {
-            spawn_atom(&mut commands, &asset_server, &mut meshes, &mut materials, position, atom_neutrons);
+    let level = match level_file {
+        Some(level_file) => Some(level_file.0.clone()),
+        None => get_level(game_stats.level)
+    };
+
+    if let Some(level) = level {
+        let atoms = level.all_atoms();
+
+        for atom in &atoms {
+            let split_pattern = atom.split_pattern.clone()
+                .or_else(|| level.split_patterns.iter().find(|(num_neutrons, _)| *num_neutrons == atom.num_neutrons).map(|(_, pattern)| pattern.clone()))
+                .unwrap_or_default();
+
+            spawn_atom(&mut commands, &asset_server, &mut meshes, &mut materials, atom.position, atom.num_neutrons, split_pattern, atom.delayed_emission, atom.tag);
+        }
+
+        for (shape, position) in level.absorbers {
+            spawn_absorber(&mut commands, &mut meshes, &mut materials, position, shape);
+        }
+
+        for wall in level.walls.into_iter().chain(level.boundary.iter().flat_map(Boundary::walls)) {
+            spawn_wall(&mut commands, &mut meshes, &mut materials, wall);
         }
         
+        commands.insert_resource(Objectives::new(level.objectives, &atoms));
         commands.insert_resource(level.level_stats);
         game_stats.score = 0.;
+        game_stats.simulation_time = 0.;
+        game_stats.simulation_speed = settings.simulation_speed;
     }
 }
 
-pub fn calculate_grade(score: f64, level_stats: &LevelStats) -> &'static str {
-    let letters = ["S", "A", "B", "C", "D"];
-
-    for i in 0..letters.len() {
-        if score + f64::EPSILON >= level_stats.s_score * 0.5 + level_stats.s_score * 1./(2. + i as f64) {
-            return letters[i];
-        }
-    }
-
-    "F"
+pub fn calculate_grade(score: f64, level_stats: &LevelStats) -> &str {
+    level_stats.grade_thresholds().into_iter()
+        .find(|(_, energy)| score + f64::EPSILON >= *energy)
+        .map_or("F", |(letter, _)| letter)
 }
 
 pub fn get_level(level: i32) -> Option<Level> {
@@ -49,52 +206,140 @@ pub fn get_level(level: i32) -> Option<Level> {
         0 => {
             Some(Level {
                 //level: 0,
-                atoms: vec![(2, Vec2::new(0., 0.)), (3, Vec2::new(-100., 100.)), (3, Vec2::new(100., 100.))],
-                level_stats: LevelStats { num_neutrons: 1, s_score: 3.2e-11 * 3. }
+                atoms: level_atoms(vec![(2, Vec2::new(0., 0.)), (3, Vec2::new(-100., 100.)), (3, Vec2::new(100., 100.))]),
+                generators: vec![],
+                absorbers: vec![],
+                walls: vec![],
+                boundary: None,
+                split_patterns: vec![],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 0, 3.2e-11 * 3.)
             })
         }
         1 => {
             Some(Level {
-                atoms: vec![
+                atoms: level_atoms(vec![
                     (5, Vec2::new(0., 0.)), 
                     (1, Vec2::new(-165., 100.)), (1, Vec2::new(165., 100.)),
                     (2, Vec2::new(-315., 200.)), (3, Vec2::new(0., 200.)), (2, Vec2::new(315., 200.)),
-                ],
-                level_stats: LevelStats { num_neutrons: 1, s_score: 3.2e-11 * 6. }
+                ]),
+                generators: vec![],
+                absorbers: vec![],
+                walls: vec![],
+                boundary: None,
+                split_patterns: vec![],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 0, 3.2e-11 * 6.)
             })
         }
         2 => {
             Some(Level {
-                atoms: vec![
+                atoms: level_atoms(vec![
                     (5, Vec2::new(0., 0.)), 
                     (3, Vec2::new(-75., 75.)), (3, Vec2::new(0., 75.)), (3, Vec2::new(75., 75.)),
                     (3, Vec2::new(-225., 150.)), (3, Vec2::new(-150., 150.)), (3, Vec2::new(0., 150.)), (3, Vec2::new(150., 150.)), (3, Vec2::new(225., 150.)),
                     (3, Vec2::new(-225., 225.)), (3, Vec2::new(-150., 225.)), (3, Vec2::new(0., 225.)), (3, Vec2::new(150., 225.)), (3, Vec2::new(225., 225.))
-                ],
-                level_stats: LevelStats { num_neutrons: 1, s_score: 3.2e-11 * 14. }
+                ]),
+                generators: vec![],
+                absorbers: vec![],
+                walls: vec![],
+                boundary: None,
+                split_patterns: vec![],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 0, 3.2e-11 * 14.)
             })
         }
         3 => {
             Some(Level {
-                atoms: vec![
+                atoms: level_atoms(vec![
                     (5, Vec2::new(0., 0.)), 
                     (3, Vec2::new(-150., 75.)), (3, Vec2::new(-75., 75.)), (3, Vec2::new(0., 75.)), (3, Vec2::new(75., 75.)), (3, Vec2::new(150., 75.)),
                     (3, Vec2::new(-225., 150.)), (3, Vec2::new(-150., 150.)), (3, Vec2::new(0., 150.)), (3, Vec2::new(150., 150.)), (3, Vec2::new(225., 150.)),
                     (3, Vec2::new(-225., 225.)), (3, Vec2::new(-150., 225.)), (3, Vec2::new(-75., 225.)), (3, Vec2::new(0., 225.)), (3, Vec2::new(75., 225.)), (3, Vec2::new(150., 225.)), (3, Vec2::new(225., 225.))
-                ],
-                level_stats: LevelStats { num_neutrons: 1, s_score: 3.2e-11 * 17. }
+                ]),
+                generators: vec![],
+                absorbers: vec![],
+                walls: vec![],
+                boundary: None,
+                split_patterns: vec![],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 0, 3.2e-11 * 17.)
             })
         }
         4 => {
             Some(Level {
-                atoms: generate_atom_sphere(8000, 75, 2),
-                level_stats: LevelStats { num_neutrons: 1, s_score: 3.2e-11 * 35000. }
+                atoms: level_atoms(generate_atom_sphere(8000, 75, 2)),
+                generators: vec![],
+                absorbers: vec![],
+                walls: vec![],
+                boundary: None,
+                split_patterns: vec![],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 0, 3.2e-11 * 35000.)
+            })
+        }
+        5 => {
+            Some(Level {
+                atoms: level_atoms(vec![
+                    (5, Vec2::new(0., 0.)),
+                    (3, Vec2::new(-150., 0.)), (3, Vec2::new(150., 0.)), (3, Vec2::new(0., 150.)), (3, Vec2::new(0., -150.)),
+                    (2, Vec2::new(-150., 150.)), (2, Vec2::new(150., 150.)), (2, Vec2::new(-150., -150.)), (2, Vec2::new(150., -150.))
+                ]),
+                generators: vec![],
+                absorbers: vec![
+                    (AbsorberShape::Rectangle { size: Vec2::new(360., 20.) }, Vec2::new(0., 260.)),
+                    (AbsorberShape::Rectangle { size: Vec2::new(20., 360.) }, Vec2::new(-260., 0.)),
+                    (AbsorberShape::Circle { radius: 40. }, Vec2::new(260., -260.))
+                ],
+                walls: vec![],
+                boundary: Some(Boundary::rectangle(Vec2::new(720., 720.), 0.6)),
+                split_patterns: vec![],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 2, 3.2e-11 * 9.)
+            })
+        }
+        6 => {
+            Some(Level {
+                atoms: [
+                    vec![LevelAtom {
+                        num_neutrons: 6,
+                        position: Vec2::new(0., 0.),
+                        split_pattern: Some(SplitPattern { shape: SplitShape::Symmetric { spread: PI * 5./3. }, jitter: 0., speed_multipliers: vec![1., 0.6] }),
+                        delayed_emission: None,
+                        tag: None
+                    }],
+                    // the inner atoms hold their neutron back for a couple of seconds
+                    level_atoms(vec![
+                        (1, Vec2::new(-200., 0.)), (1, Vec2::new(200., 0.)), (1, Vec2::new(0., -200.)), (1, Vec2::new(0., 200.))
+                    ]).into_iter()
+                        .map(|atom| LevelAtom { delayed_emission: Some(DelayedEmission { num_neutrons: 1, delay: 2. }), ..atom })
+                        .collect(),
+                    level_atoms(vec![
+                        (2, Vec2::new(-300., 250.)), (2, Vec2::new(300., 250.)), (2, Vec2::new(-300., -250.)), (2, Vec2::new(300., -250.))
+                    ])
+                ].concat(),
+                generators: vec![],
+                absorbers: vec![],
+                walls: vec![
+                    Wall::new(Vec2::new(-120., 120.), Vec2::new(120., 120.), 1.),
+                    Wall::new(Vec2::new(-120., -120.), Vec2::new(120., -120.), 1.)
+                ],
+                boundary: Some(Boundary::Circle { radius: 480., restitution: 0.9 }),
+                split_patterns: vec![
+                    (2, SplitPattern { shape: SplitShape::Asymmetric { start: -PI/6., end: PI/2. }, jitter: 0.1, speed_multipliers: vec![] })
+                ],
+                objectives: vec![],
+                level_stats: LevelStats::new(1, 0, 3.2e-11 * 9.)
             })
         }
         _ => { None }
     }
 }
 
+fn level_atoms(atoms: Vec<(i32, Vec2)>) -> Vec<LevelAtom> {
+    atoms.into_iter().map(LevelAtom::from).collect()
+}
+
 fn generate_atom_sphere(radius: i64, distance_apart: usize, num_neutrons: i32) -> Vec<(i32, Vec2)> {
     let mut vec = Vec::new();
 
@@ -109,4 +354,4 @@ fn generate_atom_sphere(radius: i64, distance_apart: usize, num_neutrons: i32) -
     }
 
     vec
-}
\ No newline at end of file
+}
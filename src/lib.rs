@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, render::view::RenderLayers, time::TimeUpdateStrategy};
+
+use atom::AtomPlugin;
+use collision::{CollisionEvent, CollisionPlugin};
+use criticality::CriticalityPlugin;
+use effects::EffectsPlugin;
+use endless::EndlessPlugin;
+use fragment::DecayingFragment;
+use gamepad::GamepadPlugin;
+use graphs::GraphsPlugin;
+use heatmap::HeatmapPlugin;
+use hud::HudPlugin;
+use input::InputPlugin;
+use level_manager::LevelPlugin;
+use menu::MenuPlugin;
+use minimap::{MinimapPlugin, MAIN_CAMERA_LAYER};
+use neutron::{Neutron, NeutronPlugin};
+use objective::ObjectivePlugin;
+use power::PowerPlugin;
+use player_controls::PlayerControlsPlugin;
+use precision::PrecisionPlugin;
+use settings::{Settings, SettingsPlugin};
+use telemetry::TelemetryPlugin;
+use trail::TrailPlugin;
+
+pub mod atom;
+pub mod absorber;
+pub mod wall;
+pub mod fragment;
+pub mod generator;
+pub mod endless;
+pub mod objective;
+pub mod power;
+pub mod criticality;
+pub mod graphs;
+pub mod telemetry;
+pub mod effects;
+pub mod trail;
+pub mod heatmap;
+pub mod minimap;
+pub mod settings;
+pub mod input;
+pub mod gamepad;
+pub mod precision;
+pub mod cli;
+pub mod neutron;
+pub mod collision;
+pub mod player_controls;
+pub mod level_manager;
+pub mod util;
+pub mod hud;
+pub mod menu;
+
+pub const ENERGY_RELEASED: f64 = 3.2e-11;
+pub const MIN_SIMULATION_SPEED: f32 = 0.1;
+pub const MAX_SIMULATION_SPEED: f32 = 5.;
+pub const SIMULATION_SPEED_STEP: f32 = 0.1;
+/// Time every update of a headless app advances by, one 60 fps frame however long it really took.
+pub const HEADLESS_TICK: Duration = Duration::from_nanos(16_666_667);
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Default)]
+pub enum GameState {
+    #[default]
+    MENU,
+    SETUP,
+    GAME,
+    /// The reaction has died out, the level is left as it ended for the player to look over.
+    RESULTS,
+    PAUSED,
+    SETTINGS,
+    CONTROLS
+}
+
+#[derive(Resource, Debug)]
+pub struct GameStats {
+    pub score: f64,
+    pub level: i32,
+    pub simulation_speed: f32,
+    /// Seconds simulated since the current run started, scaled by the simulation speed.
+    pub simulation_time: f32
+}
+
+impl Default for GameStats {
+    fn default() -> Self {
+        GameStats { score: 0., level: 0, simulation_speed: 0.5, simulation_time: 0. }
+    }
+}
+
+/// The camera the player looks through, as opposed to the minimap's.
+#[derive(Component)]
+pub struct MainCamera;
+
+/// The whole game. Expects `DefaultPlugins`; add a `LaunchOptions` after it to skip the menu.
+pub struct NucleusPlugin;
+
+/// Just the levels and the reaction, with nothing drawn or controlled by the player, so it also runs headless
+/// under `MinimalPlugins` with `AssetPlugin` and the `Mesh`, `ColorMaterial` and `Font` assets.
+pub struct SimulationPlugin;
+
+impl Plugin for NucleusPlugin {
+    fn build(&self, app: &mut App) {
+        // background color
+        app.insert_resource(ClearColor(Color::BLACK))
+
+            .add_plugins((SimulationPlugin, HudPlugin, MenuPlugin, PlayerControlsPlugin, EndlessPlugin))
+            .add_plugins((SettingsPlugin, InputPlugin, GamepadPlugin, PrecisionPlugin, MinimapPlugin))
+            .add_plugins((CriticalityPlugin, GraphsPlugin, TelemetryPlugin, EffectsPlugin, TrailPlugin, HeatmapPlugin))
+
+            .add_systems(Startup, main_setup);
+    }
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .init_resource::<GameStats>()
+            // replaced by the saved settings when SettingsPlugin is added
+            .init_resource::<Settings>()
+
+            .add_plugins((AtomPlugin, NeutronPlugin, CollisionPlugin, LevelPlugin, ObjectivePlugin, PowerPlugin))
+
+            .add_systems(Update, advance_simulation_time.run_if(in_state(GameState::GAME)))
+            .add_systems(Update, check_reaction_finished.run_if(in_state(GameState::GAME)));
+    }
+}
+
+/// An app with nothing but the simulation, to be stepped by hand with `App::update`.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), SimulationPlugin))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Font>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_TICK));
+
+    app
+}
+
+/// Run condition for anything that works while a level is on screen, from setup to the results.
+pub fn in_level(game_state: Res<State<GameState>>) -> bool {
+    matches!(game_state.get(), GameState::SETUP | GameState::GAME | GameState::PAUSED | GameState::RESULTS)
+}
+
+/// Run condition for anything that works once a run has started, paused or finished.
+pub fn in_run(game_state: Res<State<GameState>>) -> bool {
+    matches!(game_state.get(), GameState::GAME | GameState::PAUSED | GameState::RESULTS)
+}
+
+fn main_setup(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), RenderLayers::from_layers(&[0, MAIN_CAMERA_LAYER]), MainCamera));
+}
+
+fn check_reaction_finished(
+    mut events: EventReader<CollisionEvent>,
+    neutron_q: Query<(), With<Neutron>>,
+    fragment_q: Query<(), With<DecayingFragment>>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    // a fission's event arrives a frame before its neutrons, which are spawned through commands,
+    // so a fission not yet turned into neutrons still counts as a live reaction
+    if events.read().count() > 0 || !neutron_q.is_empty() || !fragment_q.is_empty() {
+        return;
+    }
+
+    next_state.set(GameState::RESULTS);
+}
+
+fn advance_simulation_time(
+    time: Res<Time>,
+    mut game_stats: ResMut<GameStats>
+) {
+    game_stats.simulation_time += time.delta_seconds() * game_stats.simulation_speed;
+}
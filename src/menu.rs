@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{GameState, GameStats};
+use crate::{endless::{EndlessButton, EndlessSeed, EndlessStatusText}, level_manager::LevelFile, GameState, GameStats};
 
 #[derive(Component)]
 pub struct Menu;
@@ -10,10 +10,23 @@ pub struct LevelValue {
     level: i32
 }
 
+#[derive(Component)]
+pub struct SettingsButton;
+
 pub const NORMAL_BUTTON: Color = Color::BLACK;
 pub const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.1, 0.);
 pub const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.15, 0.);
 
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MENU), main_menu_setup)
+            .add_systems(OnExit(GameState::MENU), main_menu_cleanup)
+            .add_systems(Update, button_system.run_if(in_state(GameState::MENU)));
+    }
+}
+
 pub fn main_menu_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>
@@ -56,9 +69,12 @@ pub fn main_menu_setup(
             top: Val::Percent(50.),
             width: Val::Percent(90.),
             height: Val::Percent(40.),
+            flex_wrap: FlexWrap::Wrap,
             justify_content: JustifyContent::Center,
             align_items: AlignItems::Center,
+            align_content: AlignContent::Center,
             column_gap: Val::Px(24.),
+            row_gap: Val::Px(24.),
             border: UiRect::all(Val::Px(2.)),
             ..default()
         },
@@ -195,7 +211,119 @@ pub fn main_menu_setup(
                     TextStyle { font: font.clone(), font_size: 48., color: Color::ORANGE }
                 ));
             });
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(100.),
+                        height: Val::Px(100.),
+                        border: UiRect::all(Val::Px(3.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    border_color: Color::ORANGE.into(),
+                    ..default()
+                },
+                LevelValue {
+                    level: 5
+                }
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "5", 
+                    TextStyle { font: font.clone(), font_size: 48., color: Color::ORANGE }
+                ));
+            });
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(100.),
+                        height: Val::Px(100.),
+                        border: UiRect::all(Val::Px(3.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    border_color: Color::ORANGE.into(),
+                    ..default()
+                },
+                LevelValue {
+                    level: 6
+                }
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "6", 
+                    TextStyle { font: font.clone(), font_size: 48., color: Color::ORANGE }
+                ));
+            });
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(250.),
+                        height: Val::Px(100.),
+                        border: UiRect::all(Val::Px(3.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    border_color: Color::ORANGE.into(),
+                    ..default()
+                },
+                SettingsButton
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "Settings", 
+                    TextStyle { font: font.clone(), font_size: 48., color: Color::ORANGE }
+                ));
+            });
+        })
+        .with_children(|parent| {
+            for (endless_button, label) in [(EndlessButton::Daily, "Daily"), (EndlessButton::Random, "Random")] {
+                parent.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(250.),
+                            height: Val::Px(100.),
+                            border: UiRect::all(Val::Px(3.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: Color::ORANGE.into(),
+                        ..default()
+                    },
+                    endless_button
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        label, 
+                        TextStyle { font: font.clone(), font_size: 48., color: Color::ORANGE }
+                    ));
+                });
+            }
         });
+    }).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "", 
+                TextStyle { font: font.clone(), font_size: 24., color: Color::ORANGE }
+            ).with_style(
+                Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(16.),
+                    ..default()
+                }
+            ),
+            EndlessStatusText
+        ));
     });
 }
 
@@ -206,17 +334,30 @@ pub fn main_menu_cleanup(
     menu_q.for_each(|menu_entity| commands.entity(menu_entity).despawn_recursive());
 }
 
+#[allow(clippy::type_complexity)]
 pub fn button_system(
-    mut interaction_q: Query<(&Interaction, &mut BackgroundColor, &LevelValue), (Changed<Interaction>, With<Button>)>,
+    mut commands: Commands,
+    mut interaction_q: Query<(&Interaction, &mut BackgroundColor, Option<&LevelValue>, Option<&SettingsButton>), (Changed<Interaction>, With<Button>)>,
     mut next_state: ResMut<NextState<GameState>>,
     mut game_stats: ResMut<GameStats>
 ) {
-    for (interaction, mut bg_color, level_value) in &mut interaction_q {
+    for (interaction, mut bg_color, level_value, settings_button) in &mut interaction_q {
         match *interaction {
             Interaction::Pressed => {
                 *bg_color = PRESSED_BUTTON.into();
-                game_stats.level = level_value.level;
-                next_state.set(GameState::SETUP);
+                match (level_value, settings_button) {
+                    (Some(level_value), _) => {
+                        // back to the built in levels from a generated or loaded one
+                        commands.remove_resource::<LevelFile>();
+                        commands.remove_resource::<EndlessSeed>();
+
+                        game_stats.level = level_value.level;
+                        next_state.set(GameState::SETUP);
+                    },
+                    (None, Some(_)) => next_state.set(GameState::SETTINGS),
+                    // the endless buttons are handled by their own system
+                    (None, None) => ()
+                }
             }
             Interaction::Hovered => {
                 *bg_color = HOVERED_BUTTON.into();
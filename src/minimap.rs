@@ -0,0 +1,262 @@
+use bevy::{prelude::*, core_pipeline::clear_color::ClearColorConfig, render::{camera::RenderTarget, render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages}, view::RenderLayers}};
+
+use crate::{atom::{atom_color, Atom, ATOM_SIZE}, collision::Collider, neutron::{Neutron, NEUTRON_COLOR, NEUTRON_SIZE}, objective::AtomTag, hud::Hud, input::Pointer, player_controls::CameraFollow, wall::Wall, MainCamera, in_level, level_manager::LevelSetupSet, GameState};
+
+/// Only the minimap camera sees this layer.
+pub const MINIMAP_LAYER: u8 = 1;
+/// Only the main camera sees this layer, gizmos go here so the graphs don't show up on the minimap.
+pub const MAIN_CAMERA_LAYER: u8 = 2;
+
+/// Width and height of the minimap texture in pixels.
+const MINIMAP_RESOLUTION: u32 = 256;
+/// Width and height of the minimap on screen.
+const MINIMAP_UI_SIZE: f32 = 200.;
+const MINIMAP_MARGIN: f32 = 1.1;
+const MINIMAP_BACKGROUND: Color = Color::rgb(0.05, 0.05, 0.1);
+/// Thickness of the viewport outline in minimap pixels.
+const VIEWPORT_THICKNESS: f32 = 2.;
+const VIEWPORT_Z: f32 = 10.;
+/// Smallest an atom or neutron is drawn on the minimap, in minimap pixels, so they still show on huge levels.
+const MARKER_MIN_PIXELS: f32 = 3.;
+/// Behind the atom or neutron itself, which covers it whenever it is big enough to see.
+const MARKER_Z: f32 = -0.5;
+
+#[derive(Resource)]
+pub struct Minimap {
+    image: Handle<Image>
+}
+
+#[derive(Component)]
+pub struct MinimapCamera;
+
+#[derive(Component)]
+pub struct MinimapImage;
+
+/// A dot only the minimap draws under an atom or neutron, never smaller than `MARKER_MIN_PIXELS`.
+#[derive(Component)]
+pub struct MinimapMarker {
+    /// Diameter of what it marks, in world units.
+    size: f32
+}
+
+/// One side of the outline showing what the main camera can see.
+#[derive(Component)]
+pub struct ViewportEdge {
+    horizontal: bool,
+    side: f32
+}
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, minimap_setup)
+            .add_systems(OnEnter(GameState::SETUP), (minimap_ui_setup.in_set(LevelSetupSet::Spawn), minimap_fit_level.in_set(LevelSetupSet::Frame)))
+            .add_systems(Update, (minimap_click, minimap_viewport_update, minimap_markers_spawn, minimap_markers_resize.after(minimap_markers_spawn)).run_if(in_level));
+    }
+}
+
+pub fn minimap_setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut gizmo_config: ResMut<GizmoConfig>
+) {
+    let size = Extent3d { width: MINIMAP_RESOLUTION, height: MINIMAP_RESOLUTION, ..default() };
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[]
+        },
+        ..default()
+    };
+    image.resize(size);
+
+    let image = images.add(image);
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera { order: -1, target: RenderTarget::Image(image.clone()), ..default() },
+            camera_2d: Camera2d { clear_color: ClearColorConfig::Custom(MINIMAP_BACKGROUND) },
+            ..default()
+        },
+        UiCameraConfig { show_ui: false },
+        RenderLayers::from_layers(&[0, MINIMAP_LAYER]),
+        MinimapCamera
+    ));
+
+    for (horizontal, side) in [(true, 1.), (true, -1.), (false, 1.), (false, -1.)] {
+        commands.spawn((
+            SpriteBundle { transform: Transform::from_xyz(0., 0., VIEWPORT_Z), ..default() },
+            RenderLayers::layer(MINIMAP_LAYER),
+            ViewportEdge { horizontal, side }
+        ));
+    }
+
+    gizmo_config.render_layers = RenderLayers::layer(MAIN_CAMERA_LAYER);
+
+    commands.insert_resource(Minimap { image });
+}
+
+pub fn minimap_ui_setup(
+    mut commands: Commands,
+    minimap: Res<Minimap>
+) {
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(35.),
+                right: Val::Px(5.),
+                width: Val::Px(MINIMAP_UI_SIZE),
+                height: Val::Px(MINIMAP_UI_SIZE),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            image: UiImage::new(minimap.image.clone()),
+            ..default()
+        },
+        BorderColor(Color::ORANGE),
+        Interaction::default(),
+        Hud,
+        MinimapImage
+    ));
+}
+
+/// Frames the whole level in the minimap once it has been spawned.
+pub fn minimap_fit_level(
+    atom_q: Query<(&Transform, &Collider), With<Atom>>,
+    wall_q: Query<&Wall>,
+    mut minimap_camera_q: Query<&mut Transform, (With<MinimapCamera>, Without<Atom>)>
+) {
+    let mut minimap_transform = minimap_camera_q.single_mut();
+
+    let mut bounds_min = Vec2::splat(f32::INFINITY);
+    let mut bounds_max = Vec2::splat(f32::NEG_INFINITY);
+
+    for (atom_transform, atom_collider) in &atom_q {
+        bounds_min = bounds_min.min(atom_transform.translation.xy() - atom_collider.radius);
+        bounds_max = bounds_max.max(atom_transform.translation.xy() + atom_collider.radius);
+    }
+
+    for wall in &wall_q {
+        bounds_min = bounds_min.min(wall.start.min(wall.end));
+        bounds_max = bounds_max.max(wall.start.max(wall.end));
+    }
+
+    if bounds_min.x > bounds_max.x {
+        return;
+    }
+
+    let scale = (bounds_max - bounds_min).max_element() * MINIMAP_MARGIN / MINIMAP_RESOLUTION as f32;
+
+    minimap_transform.translation = Vec3::from(((bounds_min + bounds_max) / 2., minimap_transform.translation.z));
+    minimap_transform.scale = Vec3::splat(scale);
+}
+
+#[allow(clippy::type_complexity)]
+pub fn minimap_viewport_update(
+    window_q: Query<&Window>,
+    main_camera_q: Query<&Transform, With<MainCamera>>,
+    minimap_camera_q: Query<&Transform, (With<MinimapCamera>, Without<MainCamera>)>,
+    mut edge_q: Query<(&mut Transform, &mut Sprite, &ViewportEdge), (Without<MainCamera>, Without<MinimapCamera>)>
+) {
+    let window = window_q.single();
+    let main_transform = main_camera_q.single();
+    let thickness = VIEWPORT_THICKNESS * minimap_camera_q.single().scale.x;
+
+    let centre = main_transform.translation.xy();
+    let half_size = Vec2::new(window.width(), window.height()) / 2. * main_transform.scale.x;
+
+    for (mut edge_transform, mut edge_sprite, edge) in &mut edge_q {
+        let (offset, size) = if edge.horizontal {
+            (Vec2::new(0., half_size.y * edge.side), Vec2::new(half_size.x * 2. + thickness, thickness))
+        } else {
+            (Vec2::new(half_size.x * edge.side, 0.), Vec2::new(thickness, half_size.y * 2. + thickness))
+        };
+
+        edge_transform.translation = Vec3::from((centre + offset, VIEWPORT_Z));
+        edge_sprite.custom_size = Some(size);
+    }
+}
+
+pub fn minimap_markers_spawn(
+    mut commands: Commands,
+    atom_q: Query<(Entity, Option<&AtomTag>), Added<Atom>>,
+    neutron_q: Query<Entity, Added<Neutron>>
+) {
+    let atoms = atom_q.iter().map(|(atom_entity, tag)| (atom_entity, atom_color(tag.copied()), ATOM_SIZE * 2.));
+    let neutrons = neutron_q.iter().map(|neutron_entity| (neutron_entity, NEUTRON_COLOR, NEUTRON_SIZE * 2.));
+
+    for (entity, color, size) in atoms.chain(neutrons) {
+        let marker = (
+            SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                transform: Transform::from_xyz(0., 0., MARKER_Z),
+                ..default()
+            },
+            RenderLayers::layer(MINIMAP_LAYER),
+            MinimapMarker { size }
+        );
+
+        // a neutron can be absorbed or split before this is applied
+        commands.add(move |world: &mut World| {
+            if world.get_entity(entity).is_some() {
+                let marker_entity = world.spawn(marker).id();
+                world.entity_mut(entity).add_child(marker_entity);
+            }
+        });
+    }
+}
+
+/// Sizes new markers, and all of them whenever the minimap zooms.
+pub fn minimap_markers_resize(
+    minimap_camera_q: Query<Ref<Transform>, With<MinimapCamera>>,
+    mut marker_q: Query<(Ref<MinimapMarker>, &mut Sprite)>
+) {
+    let minimap_transform = minimap_camera_q.single();
+    let min_size = MARKER_MIN_PIXELS * minimap_transform.scale.x;
+
+    for (marker, mut sprite) in &mut marker_q {
+        if minimap_transform.is_changed() || marker.is_added() {
+            sprite.custom_size = Some(Vec2::splat(marker.size.max(min_size)));
+        }
+    }
+}
+
+/// Moves the main camera to wherever the minimap is clicked, or dragged across.
+pub fn minimap_click(
+    pointer: Pointer,
+    minimap_q: Query<(&Interaction, &Node, &GlobalTransform), With<MinimapImage>>,
+    minimap_camera_q: Query<&Transform, (With<MinimapCamera>, Without<MainCamera>)>,
+    mut main_camera_q: Query<&mut Transform, With<MainCamera>>,
+    mut camera_follow: ResMut<CameraFollow>
+) {
+    let Ok((interaction, minimap_node, minimap_global_transform)) = minimap_q.get_single() else {
+        return;
+    };
+
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+
+    let Some(cursor) = pointer.screen_position() else {
+        return;
+    };
+
+    let minimap_transform = minimap_camera_q.single();
+    let mut main_transform = main_camera_q.single_mut();
+
+    // -0.5 to 0.5 across the minimap, with y pointing down like the cursor
+    let fraction = (cursor - minimap_global_transform.translation().xy()) / minimap_node.size();
+    let world_position = minimap_transform.translation.xy() + fraction * Vec2::new(1., -1.) * MINIMAP_RESOLUTION as f32 * minimap_transform.scale.x;
+
+    main_transform.translation = Vec3::from((world_position, main_transform.translation.z));
+    camera_follow.enabled = false;
+}
@@ -1,39 +1,142 @@
 use std::f32::consts::PI;
 
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use rand::Rng;
+use serde::Deserialize;
 
-use crate::{collision::*, GameStats};
+use crate::{collision::*, wall::{Wall, move_with_walls}, GameStats, input::Pointer, level_manager::LevelSetupSet, GameState};
 
 pub const NEUTRON_SIZE: f32 = 10.;
-const NEUTRON_COLOR: Color = Color::rgb(0.3, 0.3, 1.0);
+pub const NEUTRON_COLOR: Color = Color::rgb(0.3, 0.3, 1.0);
 const NEUTRON_MAX_DISTANCE_SQUARED: f32 = 1e9;
+/// Neutrons slowed below this by walls are considered absorbed.
+const NEUTRON_MIN_SPEED: f32 = 1.;
 
 #[derive(Component, Debug, Clone)]
 pub struct Neutron {
-    pub velocity: Vec2
+    pub velocity: Vec2,
+    /// How many fissions separate this neutron from the one the player placed, which is generation 0.
+    pub generation: u32
 }
 
 #[derive(Component)]
 pub struct PlacementMarker;
 
+/// How the neutrons released by a fission fan out, relative to the neutron that caused it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum SplitShape {
+    /// The original hand tuned fans for up to five neutrons, widening to an even 120° fan beyond that.
+    Classic,
+    /// An even fan `spread` radians wide, centred on the incoming direction.
+    Symmetric { spread: f32 },
+    /// An even fan from `start` to `end` radians, measured anticlockwise from the incoming direction.
+    Asymmetric { start: f32, end: f32 }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SplitPattern {
+    pub shape: SplitShape,
+    /// Largest random rotation, in radians, applied to each released neutron.
+    pub jitter: f32,
+    /// Speed of each released neutron relative to the incoming one, repeating when there are more neutrons than entries.
+    /// Empty keeps the incoming speed.
+    pub speed_multipliers: Vec<f32>
+}
+
+impl Default for SplitPattern {
+    fn default() -> Self {
+        SplitPattern { shape: SplitShape::Classic, jitter: 0., speed_multipliers: Vec::new() }
+    }
+}
+
+impl SplitPattern {
+    /// The direction of each released neutron before jitter, as an angle from the incoming direction.
+    pub fn angles(&self, num_split: usize) -> Vec<f32> {
+        match self.shape {
+            SplitShape::Classic => match num_split {
+                1 => vec![0.],
+                2 => vec![PI/4., -PI/4.],
+                3 => vec![PI/3., 0., -PI/3.],
+                4 => vec![PI/3., PI/6., -PI/6., -PI/3.],
+                5 => vec![PI/3., PI/6., 0., -PI/6., -PI/3.],
+                _ => even_fan(PI/3., -PI/3., num_split)
+            },
+            SplitShape::Symmetric { spread } => even_fan(spread / 2., -spread / 2., num_split),
+            SplitShape::Asymmetric { start, end } => even_fan(start, end, num_split)
+        }
+    }
+
+    pub fn speed_multiplier(&self, index: usize) -> f32 {
+        if self.speed_multipliers.is_empty() {
+            return 1.;
+        }
+
+        self.speed_multipliers[index % self.speed_multipliers.len()]
+    }
+}
+
+fn even_fan(start: f32, end: f32, num_split: usize) -> Vec<f32> {
+    if num_split == 1 {
+        return vec![(start + end) / 2.];
+    }
+
+    (0..num_split)
+        .map(|i| start + (end - start) * i as f32 / (num_split - 1) as f32)
+        .collect()
+}
+
 #[derive(Component, Default)]
 pub struct PlacementPointer {
     set_vel: bool
 }
 
+pub struct NeutronPlugin;
+
+impl Plugin for NeutronPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::SETUP), neutron_cleanup.in_set(LevelSetupSet::Cleanup))
+            .add_systems(Update, neutron_motion.run_if(in_state(GameState::GAME)));
+    }
+}
+
 pub fn neutron_motion(
     par_commands: ParallelCommands,
-    mut neutrons: Query<(Entity, &mut Transform, &Neutron)>,
+    mut neutrons: Query<(Entity, &mut Transform, &mut Neutron, &Collider)>,
+    walls_q: Query<&Wall>,
     time: Res<Time>,
     game_stats: Res<GameStats>
 ) {
-    neutrons.par_iter_mut().for_each(|(neutron_entity, mut neutron_transform, neutron)| {
+    let walls: Vec<Wall> = walls_q.iter().copied().collect();
+
+    neutrons.par_iter_mut().for_each(|(neutron_entity, mut neutron_transform, mut neutron, neutron_collider)| {
         if neutron_transform.translation.xy().length_squared() > NEUTRON_MAX_DISTANCE_SQUARED {
-            par_commands.command_scope(|mut commands| commands.entity(neutron_entity).despawn());
+            par_commands.command_scope(|mut commands| commands.entity(neutron_entity).despawn_recursive());
             return;
         }
 
-        neutron_transform.translation += Vec3::from((neutron.velocity * time.delta_seconds() * game_stats.simulation_speed, 0.));
+        let displacement = neutron.velocity * time.delta_seconds() * game_stats.simulation_speed;
+
+        if walls.is_empty() {
+            neutron_transform.translation += Vec3::from((displacement, 0.));
+            return;
+        }
+
+        let mut position = neutron_transform.translation.xy();
+        let mut velocity = neutron.velocity;
+
+        move_with_walls(&mut position, &mut velocity, displacement, neutron_collider.radius, &walls);
+
+        neutron_transform.translation = Vec3::from((position, neutron_transform.translation.z));
+
+        if velocity != neutron.velocity {
+            if velocity.length() < NEUTRON_MIN_SPEED {
+                par_commands.command_scope(|mut commands| commands.entity(neutron_entity).despawn_recursive());
+                return;
+            }
+
+            neutron.velocity = velocity;
+        }
     });
 }
 
@@ -42,8 +145,9 @@ pub fn spawn_neutron(
     meshes: &mut ResMut<Assets<Mesh>>, 
     materials: &mut ResMut<Assets<ColorMaterial>>,
     position: Vec2, 
-    velocity: Vec2
-) {
+    velocity: Vec2,
+    generation: u32
+) -> Entity {
     commands.spawn((
         MaterialMesh2dBundle {
             mesh: meshes.add(shape::Circle::new(NEUTRON_SIZE).into()).into(),
@@ -54,9 +158,9 @@ pub fn spawn_neutron(
             },
             ..default()
         },
-        Neutron { velocity: velocity },
+        Neutron { velocity, generation },
         Collider { radius: NEUTRON_SIZE * 0.75 }
-    ));
+    )).id()
 }
 
 pub fn spawn_neutron_with_marker(
@@ -64,21 +168,10 @@ pub fn spawn_neutron_with_marker(
     meshes: &mut ResMut<Assets<Mesh>>, 
     materials: &mut ResMut<Assets<ColorMaterial>>,
     position: Vec2
-) {
-    commands.spawn((
-        MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::new(NEUTRON_SIZE).into()).into(),
-            material: materials.add(ColorMaterial::from(NEUTRON_COLOR)),
-            transform: Transform {
-                translation: Vec3::from((position, 1.)),
-                ..default()
-            },
-            ..default()
-        },
-        Neutron { velocity: Vec2::ZERO },
-        Collider { radius: NEUTRON_SIZE * 0.75 },
-        PlacementMarker
-    )).with_children(|parent| {
+) -> Entity {
+    let neutron_entity = spawn_neutron(commands, meshes, materials, position, Vec2::ZERO, 0);
+
+    commands.entity(neutron_entity).insert(PlacementMarker).with_children(|parent| {
         parent.spawn((
             MaterialMesh2dBundle {
                 mesh: meshes.add(shape::Quad { size: Vec2::new(40., NEUTRON_SIZE/2.), flip: false }.into()).into(),
@@ -91,23 +184,18 @@ pub fn spawn_neutron_with_marker(
             },
             PlacementPointer::default()
         ));
-    });
+    }).id()
 }
 
 /*
     a lot taken https://github.com/bevyengine/bevy/blob/main/examples/2d/rotation.rs
 */
 pub fn pointer_follow_cursor(
-    window_q: Query<&Window>,
-    camera_q: Query<(&Camera, &GlobalTransform)>,
+    pointer: Pointer,
     mut pointer_q: Query<(&mut Transform, &PlacementPointer, &Parent)>,
     parent_q: Query<&GlobalTransform, (With<Neutron>, With<PlacementMarker>)>
 ) {
-    let window = window_q.single();
-    let (camera, camera_transform) = camera_q.single();
-
-    if let Some(cursor_pos) = window.cursor_position()
-    .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) {
+    if let Some(cursor_pos) = pointer.world_position() {
         for (mut pointer_transform, placement_pointer, pointer_parent) in &mut pointer_q {
             if placement_pointer.set_vel {
                 continue;
@@ -134,66 +222,28 @@ pub fn pointer_follow_cursor(
     }
 }
 
-pub fn calculate_split_trajectories(neutron_velocity: Vec2, num_split: i32) -> Option<Vec<Vec2>> {
+pub fn calculate_split_trajectories(
+    neutron_velocity: Vec2, 
+    num_split: i32, 
+    split_pattern: &SplitPattern, 
+    rng: &mut impl Rng
+) -> Option<Vec<Vec2>> {
+    if num_split < 1 {
+        error!("Negative neutrons! {}", num_split);
+        return None;
+    }
+
     let normalized_velocity = neutron_velocity.normalize();
+    let speed = neutron_velocity.length();
 
-    let vec = match num_split {
-        1 => {
-            vec![neutron_velocity]
-        },
-        2 => {
-            let rotation1 = Vec2::from_angle(PI/4.);
-            let rotation2 = Vec2::from_angle((7. * PI)/4.);
-
-            vec![
-                rotation1.rotate(normalized_velocity) * neutron_velocity.length(), 
-                rotation2.rotate(normalized_velocity) * neutron_velocity.length()
-            ]
-        },
-        3 => {
-            let rotation1 = Vec2::from_angle(PI/3.);
-            let rotation2 = Vec2::from_angle(0.);
-            let rotation3 = Vec2::from_angle((5. * PI)/3.);
-
-            vec![
-                rotation1.rotate(normalized_velocity) * neutron_velocity.length(), 
-                rotation2.rotate(normalized_velocity) * neutron_velocity.length(),
-                rotation3.rotate(normalized_velocity) * neutron_velocity.length()
-            ]
-        },
-        4 => {
-            let rotation1 = Vec2::from_angle(PI/3.);
-            let rotation2 = Vec2::from_angle(PI/6.);
-            let rotation3 = Vec2::from_angle((11. * PI)/6.);
-            let rotation4 = Vec2::from_angle((5. * PI)/3.);
-
-            vec![
-                rotation1.rotate(normalized_velocity) * neutron_velocity.length(), 
-                rotation2.rotate(normalized_velocity) * neutron_velocity.length(),
-                rotation3.rotate(normalized_velocity) * neutron_velocity.length(), 
-                rotation4.rotate(normalized_velocity) * neutron_velocity.length()
-            ]
-        },
-        5 => {
-            let rotation1 = Vec2::from_angle(PI/3.);
-            let rotation2 = Vec2::from_angle(PI/6.);
-            let rotation3 = Vec2::from_angle(0.);
-            let rotation4 = Vec2::from_angle((11. * PI)/6.);
-            let rotation5 = Vec2::from_angle((5. * PI)/3.);
-
-            vec![
-                rotation1.rotate(normalized_velocity) * neutron_velocity.length(), 
-                rotation2.rotate(normalized_velocity) * neutron_velocity.length(),
-                rotation3.rotate(normalized_velocity) * neutron_velocity.length(), 
-                rotation4.rotate(normalized_velocity) * neutron_velocity.length(),
-                rotation5.rotate(normalized_velocity) * neutron_velocity.length()
-            ]
-        }
-        _ => {
-            error!("Too many neutrons/negative neutrons! {}", num_split);
-            return None;
-        }
-    };
+    let vec = split_pattern.angles(num_split as usize).into_iter()
+        .enumerate()
+        .map(|(i, angle)| {
+            let jitter = if split_pattern.jitter > 0. { rng.gen_range(-split_pattern.jitter..=split_pattern.jitter) } else { 0. };
+
+            Vec2::from_angle(angle + jitter).rotate(normalized_velocity) * speed * split_pattern.speed_multiplier(i)
+        })
+        .collect();
 
     Some(vec)
 }
@@ -203,4 +253,112 @@ pub fn neutron_cleanup(
     neutron_q: Query<Entity, With<Neutron>>
 ) {
     neutron_q.for_each(|neutron_entity| commands.entity(neutron_entity).despawn_recursive());
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{PI, TAU};
+
+    use bevy::prelude::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{calculate_split_trajectories, SplitPattern, SplitShape};
+
+    const INCOMING: Vec2 = Vec2::new(300., 400.);
+
+    fn split(num_split: i32, split_pattern: &SplitPattern) -> Vec<Vec2> {
+        calculate_split_trajectories(INCOMING, num_split, split_pattern, &mut StdRng::seed_from_u64(0)).unwrap()
+    }
+
+    fn assert_trajectories(trajectories: &[Vec2], angles: &[f32], speeds: &[f32]) {
+        assert_eq!(trajectories.len(), angles.len());
+
+        for ((trajectory, angle), speed) in trajectories.iter().zip(angles).zip(speeds) {
+            assert!((INCOMING.angle_between(*trajectory) - angle).abs() < 1e-4, "expected angle {angle}, got {}", INCOMING.angle_between(*trajectory));
+            assert!((trajectory.length() - speed).abs() < 1e-3, "expected speed {speed}, got {}", trajectory.length());
+        }
+    }
+
+    #[test]
+    fn classic_matches_original_fans() {
+        let classic = SplitPattern::default();
+        let speed = INCOMING.length();
+
+        assert_trajectories(&split(1, &classic), &[0.], &[speed]);
+        assert_trajectories(&split(2, &classic), &[PI/4., -PI/4.], &[speed; 2]);
+        assert_trajectories(&split(3, &classic), &[PI/3., 0., -PI/3.], &[speed; 3]);
+        assert_trajectories(&split(4, &classic), &[PI/3., PI/6., -PI/6., -PI/3.], &[speed; 4]);
+        assert_trajectories(&split(5, &classic), &[PI/3., PI/6., 0., -PI/6., -PI/3.], &[speed; 5]);
+    }
+
+    #[test]
+    fn classic_supports_more_than_five() {
+        let speed = INCOMING.length();
+        let step = (2. * PI/3.) / 5.;
+
+        assert_trajectories(
+            &split(6, &SplitPattern::default()), 
+            &[PI/3., PI/3. - step, PI/3. - 2. * step, PI/3. - 3. * step, PI/3. - 4. * step, -PI/3.], 
+            &[speed; 6]
+        );
+        assert_eq!(split(12, &SplitPattern::default()).len(), 12);
+    }
+
+    #[test]
+    fn symmetric_fan_is_centred() {
+        let pattern = SplitPattern { shape: SplitShape::Symmetric { spread: PI }, ..default() };
+        let trajectories = split(7, &pattern);
+        let speed = INCOMING.length();
+
+        assert_trajectories(&trajectories, &[PI/2., PI/3., PI/6., 0., -PI/6., -PI/3., -PI/2.], &[speed; 7]);
+
+        // symmetric fans with equal speeds carry momentum straight along the incoming direction
+        let total: Vec2 = trajectories.iter().sum();
+        assert!(INCOMING.angle_between(total).abs() < 1e-4);
+    }
+
+    #[test]
+    fn asymmetric_fan_leans_to_one_side() {
+        let pattern = SplitPattern { shape: SplitShape::Asymmetric { start: 0., end: PI/2. }, ..default() };
+        let speed = INCOMING.length();
+
+        assert_trajectories(&split(3, &pattern), &[0., PI/4., PI/2.], &[speed; 3]);
+        assert_trajectories(&split(1, &pattern), &[PI/4.], &[speed]);
+
+        let total: Vec2 = split(3, &pattern).iter().sum();
+        assert!((INCOMING.angle_between(total) - PI/4.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn speed_multipliers_repeat() {
+        let pattern = SplitPattern { speed_multipliers: vec![2., 0.5], ..default() };
+        let speed = INCOMING.length();
+
+        assert_trajectories(&split(3, &pattern), &[PI/3., 0., -PI/3.], &[speed * 2., speed * 0.5, speed * 2.]);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let jitter = 0.2;
+        let pattern = SplitPattern { shape: SplitShape::Symmetric { spread: TAU / 2. }, jitter, ..default() };
+        let expected = pattern.angles(9);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..100 {
+            let trajectories = calculate_split_trajectories(INCOMING, 9, &pattern, &mut rng).unwrap();
+
+            for (trajectory, angle) in trajectories.iter().zip(&expected) {
+                assert!((INCOMING.angle_between(*trajectory) - angle).abs() <= jitter + 1e-4);
+                assert!((trajectory.length() - INCOMING.length()).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn no_neutrons_is_none() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        assert!(calculate_split_trajectories(INCOMING, 0, &SplitPattern::default(), &mut rng).is_none());
+        assert!(calculate_split_trajectories(INCOMING, -1, &SplitPattern::default(), &mut rng).is_none());
+    }
+}
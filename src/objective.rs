@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{atom::Atom, collision::collision_listener, hud::results_setup, level_manager::{calculate_grade, LevelAtom, LevelStats}, GameState, GameStats};
+
+/// Marks an atom that objectives care about. Untagged atoms are just there for the energy.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum AtomTag {
+    /// Needs to be split, drawn green.
+    Target,
+    /// Must not be split, drawn red.
+    Protected
+}
+
+/// A goal a level sets on top of releasing energy. Every objective has to be met for the run to be graded on its energy.
+#[derive(Debug, Clone, Deserialize)]
+pub enum Objective {
+    /// Split every target atom, and with `only` nothing else.
+    FissionTargets {
+        #[serde(default)]
+        only: bool
+    },
+    /// Split none of the protected atoms.
+    SpareProtected,
+    /// Release `energy` joules before `seconds` of simulated time have passed.
+    EnergyWithin { energy: f64, seconds: f32 },
+    /// Split every atom in the level.
+    ClearAll,
+    /// Keep neutrons flying for `seconds` of simulated time.
+    SustainFor { seconds: f32 }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectiveStatus {
+    #[default]
+    Pending,
+    Complete,
+    Failed
+}
+
+/// How many atoms of each kind there are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AtomCounts {
+    pub targets: usize,
+    pub protected: usize,
+    pub untagged: usize
+}
+
+/// Everything objectives are judged on, as it stands at some point in the run.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectiveProgress {
+    pub score: f64,
+    pub simulation_time: f32,
+    /// Atoms the level started with.
+    pub total: AtomCounts,
+    /// Atoms not split yet.
+    pub remaining: AtomCounts,
+    /// The reaction has died out, so anything not met yet never will be.
+    pub finished: bool
+}
+
+/// The current level's objectives and how each is going, kept up to date during the run.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Objectives {
+    pub objectives: Vec<(Objective, ObjectiveStatus)>,
+    pub total: AtomCounts
+}
+
+/// Tracks the level's objectives while the reaction runs.
+pub struct ObjectivePlugin;
+
+impl Plugin for ObjectivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Objectives>()
+            .add_systems(Update, objective_update.after(collision_listener).run_if(in_state(GameState::GAME)))
+            .add_systems(OnEnter(GameState::RESULTS), objective_finish.before(results_setup));
+    }
+}
+
+impl AtomCounts {
+    pub fn add(&mut self, tag: Option<AtomTag>) {
+        match tag {
+            Some(AtomTag::Target) => self.targets += 1,
+            Some(AtomTag::Protected) => self.protected += 1,
+            None => self.untagged += 1
+        }
+    }
+
+    pub fn all(&self) -> usize {
+        self.targets + self.protected + self.untagged
+    }
+}
+
+impl FromIterator<Option<AtomTag>> for AtomCounts {
+    fn from_iter<T: IntoIterator<Item = Option<AtomTag>>>(tags: T) -> Self {
+        tags.into_iter().fold(AtomCounts::default(), |mut counts, tag| {
+            counts.add(tag);
+            counts
+        })
+    }
+}
+
+impl Objective {
+    /// Where the objective stands given its last status. Failing is final, and so is completing
+    /// unless something split later can still spoil it.
+    pub fn evaluate(&self, previous: ObjectiveStatus, progress: &ObjectiveProgress) -> ObjectiveStatus {
+        let split = |total: usize, remaining: usize| total - remaining.min(total);
+
+        match (previous, self) {
+            (ObjectiveStatus::Failed, _) => return ObjectiveStatus::Failed,
+            (ObjectiveStatus::Complete, Objective::FissionTargets { only: true }) => (),
+            (ObjectiveStatus::Complete, _) => return ObjectiveStatus::Complete,
+            (ObjectiveStatus::Pending, _) => ()
+        }
+
+        let (complete, failed) = match *self {
+            Objective::FissionTargets { only } => (
+                progress.remaining.targets == 0,
+                only && split(progress.total.protected + progress.total.untagged, progress.remaining.protected + progress.remaining.untagged) > 0
+            ),
+            // nothing can go wrong any more once the reaction is over
+            Objective::SpareProtected => (progress.finished, progress.remaining.protected < progress.total.protected),
+            Objective::EnergyWithin { energy, seconds } => (
+                progress.score + f64::EPSILON >= energy && progress.simulation_time <= seconds,
+                progress.simulation_time > seconds
+            ),
+            Objective::ClearAll => (progress.remaining.all() == 0, false),
+            Objective::SustainFor { seconds } => (progress.simulation_time >= seconds, false)
+        };
+
+        if failed {
+            ObjectiveStatus::Failed
+        } else if complete {
+            ObjectiveStatus::Complete
+        } else if progress.finished {
+            ObjectiveStatus::Failed
+        } else {
+            ObjectiveStatus::Pending
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Objective::FissionTargets { only: false } => "Split every green atom".to_string(),
+            Objective::FissionTargets { only: true } => "Split the green atoms and nothing else".to_string(),
+            Objective::SpareProtected => "Don't split any red atoms".to_string(),
+            Objective::EnergyWithin { energy, seconds } => format!("Release {energy:.3e} J within {seconds:.1} s"),
+            Objective::ClearAll => "Leave no atom standing".to_string(),
+            Objective::SustainFor { seconds } => format!("Keep the reaction going for {seconds:.1} s")
+        }
+    }
+}
+
+impl ObjectiveStatus {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ObjectiveStatus::Pending => "[ ]",
+            ObjectiveStatus::Complete => "[x]",
+            ObjectiveStatus::Failed => "[!]"
+        }
+    }
+}
+
+impl Objectives {
+    pub fn new(objectives: Vec<Objective>, atoms: &[LevelAtom]) -> Self {
+        Objectives {
+            objectives: objectives.into_iter().map(|objective| (objective, ObjectiveStatus::Pending)).collect(),
+            total: atoms.iter().map(|atom| atom.tag).collect()
+        }
+    }
+
+    pub fn update(&mut self, progress: &ObjectiveProgress) {
+        for (objective, status) in &mut self.objectives {
+            *status = objective.evaluate(*status, progress);
+        }
+    }
+
+    pub fn any_failed(&self) -> bool {
+        self.objectives.iter().any(|(_, status)| *status == ObjectiveStatus::Failed)
+    }
+
+    /// The energy grade, or F as soon as any objective is failed.
+    pub fn grade<'a>(&self, score: f64, level_stats: &'a LevelStats) -> &'a str {
+        if self.any_failed() { "F" } else { calculate_grade(score, level_stats) }
+    }
+}
+
+fn progress(objectives: &Objectives, game_stats: &GameStats, atom_q: &Query<Option<&AtomTag>, With<Atom>>, finished: bool) -> ObjectiveProgress {
+    ObjectiveProgress {
+        score: game_stats.score,
+        simulation_time: game_stats.simulation_time,
+        total: objectives.total,
+        remaining: atom_q.iter().map(|tag| tag.copied()).collect(),
+        finished
+    }
+}
+
+pub fn objective_update(
+    mut objectives: ResMut<Objectives>,
+    game_stats: Res<GameStats>,
+    atom_q: Query<Option<&AtomTag>, With<Atom>>
+) {
+    let progress = progress(&objectives, &game_stats, &atom_q, false);
+    objectives.update(&progress);
+}
+
+/// Settles every objective still pending when the reaction dies out.
+pub fn objective_finish(
+    mut objectives: ResMut<Objectives>,
+    game_stats: Res<GameStats>,
+    atom_q: Query<Option<&AtomTag>, With<Atom>>
+) {
+    let progress = progress(&objectives, &game_stats, &atom_q, true);
+    objectives.update(&progress);
+
+    for (objective, status) in &objectives.objectives {
+        info!("{} {}", status.symbol(), objective.description());
+    }
+}
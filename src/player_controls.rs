@@ -1,14 +1,24 @@
-use bevy::{prelude::*, input::mouse::MouseWheel};
+use bevy::{prelude::*, input::{common_conditions::input_just_pressed, mouse::{MouseMotion, MouseWheel}}};
 
-use crate::{GameState, util::{clamp, clamp_vec2_by_length}, level_manager::LevelStats, collision::{Collider, is_colliding, is_cords_in_collider}, atom::Atom, neutron::{NEUTRON_SIZE, Neutron, PlacementMarker, spawn_neutron_with_marker}};
+use crate::{GameState, GameStats, MainCamera, MIN_SIMULATION_SPEED, MAX_SIMULATION_SPEED, SIMULATION_SPEED_STEP, input::{Action, Actions, Pointer, VirtualCursor}, gamepad::{node_contains, GamepadFocus}, util::{clamp, clamp_vec2_by_length}, level_manager::LevelStats, collision::{Collider, is_colliding, is_cords_in_collider, is_circle_in_rect}, atom::Atom, neutron::{NEUTRON_SIZE, Neutron, PlacementMarker, PlacementPointer, spawn_neutron_with_marker}, absorber::{Absorber, ControlRod, CONTROL_ROD_SIZE, spawn_control_rod}, in_level, level_manager::LevelSetupSet, neutron::pointer_follow_cursor, precision::precision_active};
 
-const MAX_ZOOM_OUT: f32 = 10.;
-const MAX_ZOOM_IN: f32 = 0.1;
+pub const MAX_ZOOM_OUT: f32 = 20.;
+pub const MAX_ZOOM_IN: f32 = 0.1;
+/// Zoom steps per second while a zoom key is held, one step being a notch of the scroll wheel.
+pub const KEY_ZOOM_RATE: f32 = 10.;
+pub const MIN_LAUNCH_SPEED: f32 = 0.1;
+pub const MAX_LAUNCH_SPEED: f32 = 1500.;
+/// Room left around the atoms when fitting the camera to a level.
+const FIT_LEVEL_MARGIN: f32 = 1.1;
+/// Space kept around each neutron when following them, so a lone neutron doesn't zoom all the way in.
+const FOLLOW_PADDING: f32 = 200.;
+/// How quickly the following camera catches up, higher is snappier.
+const FOLLOW_SMOOTHING: f32 = 3.;
 
 #[derive(Resource)]
 pub struct CameraOptions {
-    zoom_speed: f32,
-    movement_speed: f32
+    pub zoom_speed: f32,
+    pub movement_speed: f32
 }
 
 impl Default for CameraOptions {
@@ -17,86 +27,245 @@ impl Default for CameraOptions {
     }
 }
 
+/// Whether the camera tracks the neutrons during a run.
+#[derive(Resource, Debug, Default)]
+pub struct CameraFollow {
+    pub enabled: bool
+}
+
 #[derive(States, Default, Debug, Hash, Eq, PartialEq, Clone)]
 pub enum PlacementState {
     #[default]
     NEUTRON,
-    VELOCITY
+    VELOCITY,
+    ROD
+}
+
+/// Placing neutrons and control rods, starting and pausing runs, and moving the camera.
+pub struct PlayerControlsPlugin;
+
+impl Plugin for PlayerControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<PlacementState>()
+            .init_resource::<CameraOptions>()
+            .init_resource::<CameraFollow>()
+            .add_systems(OnEnter(GameState::SETUP), camera_fit_level.in_set(LevelSetupSet::Frame))
+            .add_systems(Update, (player_end_setup, pointer_follow_cursor, player_toggle_control_rod_mode).run_if(in_state(GameState::SETUP)))
+            .add_systems(Update, (player_place_neutrons, player_remove_neutron, player_place_control_rods, player_remove_control_rod).run_if(in_state(GameState::SETUP).and_then(not(pointer_over_ui))))
+            .add_systems(Update, player_toggle_pause.run_if(in_state(GameState::GAME).or_else(in_state(GameState::PAUSED))))
+            // the precision form takes typed minus signs
+            .add_systems(Update, player_change_simulation_speed.run_if(in_state(GameState::GAME).or_else(in_state(GameState::PAUSED)).or_else(in_state(GameState::SETUP).and_then(not(precision_active)))))
+            .add_systems(Update, (camera_follow_toggle, camera_follow.after(camera_movement).after(camera_zoom)).run_if(in_state(GameState::GAME)))
+            .add_systems(Update, (camera_zoom, camera_movement, camera_fit_level.run_if(input_just_pressed(KeyCode::F))).run_if(in_level));
+    }
 }
 
 pub fn camera_zoom(
     mut mouse_wheel_events: EventReader<MouseWheel>,
+    actions: Actions,
+    pointer: Pointer,
+    window_q: Query<&Window>,
     camera_options: Res<CameraOptions>,
-    mut cameras: Query<&mut Transform, With<Camera>>
+    time: Res<Time>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>
 ) {
+    let window = window_q.single();
     let mut camera_transform = cameras.single_mut();
 
-    for mouse_wheel_event in mouse_wheel_events.read() {
-        let zoom_value = -mouse_wheel_event.y * camera_options.zoom_speed;
-        
-        camera_transform.scale.x = clamp(camera_transform.scale.x + zoom_value, MAX_ZOOM_IN, MAX_ZOOM_OUT);
-        camera_transform.scale.y = clamp(camera_transform.scale.y + zoom_value, MAX_ZOOM_IN, MAX_ZOOM_OUT);
-        camera_transform.scale.z = clamp(camera_transform.scale.z + zoom_value, MAX_ZOOM_IN, MAX_ZOOM_OUT);
+    let mut zoom_values: Vec<f32> = mouse_wheel_events.read().map(|mouse_wheel_event| -mouse_wheel_event.y * camera_options.zoom_speed).collect();
+
+    if actions.pressed(Action::ZoomIn) {
+        zoom_values.push(-KEY_ZOOM_RATE * camera_options.zoom_speed * time.delta_seconds());
+    }
+    if actions.pressed(Action::ZoomOut) {
+        zoom_values.push(KEY_ZOOM_RATE * camera_options.zoom_speed * time.delta_seconds());
+    }
+
+    for zoom_value in zoom_values {
+        let old_scale = camera_transform.scale.x;
+        let new_scale = clamp(old_scale + zoom_value, MAX_ZOOM_IN, MAX_ZOOM_OUT);
+
+        // keep the world point under the cursor where it is
+        if let Some(cursor) = pointer.screen_position() {
+            let cursor_offset = (cursor - Vec2::new(window.width(), window.height()) / 2.) * Vec2::new(1., -1.);
+            camera_transform.translation += Vec3::from((cursor_offset * (old_scale - new_scale), 0.));
+        }
+
+        camera_transform.scale = Vec3::splat(new_scale);
     }
 }
 
 pub fn camera_movement(
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Actions,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
     camera_options: Res<CameraOptions>,
     time: Res<Time>,
-    mut cameras: Query<&mut Transform, With<Camera>>
+    mut cameras: Query<&mut Transform, With<MainCamera>>
 ) {
     let mut camera_transform = cameras.single_mut();
+    // pan the same distance on screen however far out the camera is
+    let movement_speed = camera_options.movement_speed * camera_transform.scale.x;
 
     let mut direction_x = 0.;
     let mut direction_y = 0.;
 
-    for keycode in keyboard_input.get_pressed() {
-        match keycode {
-            KeyCode::W => direction_y = camera_options.movement_speed,
-            KeyCode::S => direction_y = -camera_options.movement_speed,
-            KeyCode::A => direction_x = -camera_options.movement_speed,
-            KeyCode::D => direction_x = camera_options.movement_speed,
-            _ => ()
-        }
+    if actions.pressed(Action::PanUp) {
+        direction_y = movement_speed;
+    }
+    if actions.pressed(Action::PanDown) {
+        direction_y = -movement_speed;
+    }
+    if actions.pressed(Action::PanLeft) {
+        direction_x = -movement_speed;
+    }
+    if actions.pressed(Action::PanRight) {
+        direction_x = movement_speed;
     }
 
     camera_transform.translation.x += direction_x * time.delta_seconds();
     camera_transform.translation.y += direction_y * time.delta_seconds();
+
+    // not the right button, which removes whatever is under the cursor
+    let dragging = mouse_button_input.pressed(MouseButton::Middle);
+
+    for mouse_motion_event in mouse_motion_events.read() {
+        if dragging {
+            camera_transform.translation.x -= mouse_motion_event.delta.x * camera_transform.scale.x;
+            camera_transform.translation.y += mouse_motion_event.delta.y * camera_transform.scale.y;
+        }
+    }
 }
 
-pub fn player_end_setup(
+/// Centres the camera on the level's atoms, zoomed out far enough to see all of them.
+#[allow(clippy::type_complexity)]
+pub fn camera_fit_level(
+    window_q: Query<&Window>,
+    atom_q: Query<(&Transform, &Collider), (With<Atom>, Without<MainCamera>)>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>
+) {
+    let window = window_q.single();
+    let mut camera_transform = cameras.single_mut();
+
+    let mut bounds_min = Vec2::splat(f32::INFINITY);
+    let mut bounds_max = Vec2::splat(f32::NEG_INFINITY);
+
+    for (atom_transform, atom_collider) in &atom_q {
+        bounds_min = bounds_min.min(atom_transform.translation.xy() - atom_collider.radius);
+        bounds_max = bounds_max.max(atom_transform.translation.xy() + atom_collider.radius);
+    }
+
+    if bounds_min.x > bounds_max.x {
+        return;
+    }
+
+    let (centre, scale) = frame_bounds(window, bounds_min, bounds_max);
+
+    camera_transform.translation = Vec3::from((centre, camera_transform.translation.z));
+    camera_transform.scale = Vec3::splat(scale);
+}
+
+/// The camera position and scale that fit the given box on screen.
+fn frame_bounds(window: &Window, bounds_min: Vec2, bounds_max: Vec2) -> (Vec2, f32) {
+    let scale = ((bounds_max - bounds_min) * FIT_LEVEL_MARGIN / Vec2::new(window.width(), window.height())).max_element();
+
+    ((bounds_min + bounds_max) / 2., clamp(scale, MAX_ZOOM_IN, MAX_ZOOM_OUT))
+}
+
+pub fn camera_follow_toggle(
     keyboard_input: Res<Input<KeyCode>>,
+    mut camera_follow: ResMut<CameraFollow>
+) {
+    if keyboard_input.just_pressed(KeyCode::C) {
+        camera_follow.enabled = !camera_follow.enabled;
+    }
+}
+
+/// Eases the camera towards framing every active neutron.
+pub fn camera_follow(
+    window_q: Query<&Window>,
+    neutron_q: Query<&Transform, (With<Neutron>, Without<MainCamera>)>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+    camera_follow: Res<CameraFollow>,
+    time: Res<Time>
+) {
+    if !camera_follow.enabled {
+        return;
+    }
+
+    let window = window_q.single();
+    let mut camera_transform = cameras.single_mut();
+
+    let mut bounds_min = Vec2::splat(f32::INFINITY);
+    let mut bounds_max = Vec2::splat(f32::NEG_INFINITY);
+
+    for neutron_transform in &neutron_q {
+        bounds_min = bounds_min.min(neutron_transform.translation.xy() - FOLLOW_PADDING);
+        bounds_max = bounds_max.max(neutron_transform.translation.xy() + FOLLOW_PADDING);
+    }
+
+    // hold still once the last neutron is gone
+    if bounds_min.x > bounds_max.x {
+        return;
+    }
+
+    let (centre, scale) = frame_bounds(window, bounds_min, bounds_max);
+    let t = 1. - (-FOLLOW_SMOOTHING * time.delta_seconds()).exp();
+
+    let translation = camera_transform.translation.xy().lerp(centre, t);
+    camera_transform.translation = Vec3::from((translation, camera_transform.translation.z));
+    camera_transform.scale = Vec3::splat(camera_transform.scale.x + (scale - camera_transform.scale.x) * t);
+}
+
+/// True while the cursor is over a HUD button or the minimap, or a button has gamepad focus, so clicks there don't also place anything.
+pub fn pointer_over_ui(
+    interaction_q: Query<(&Interaction, &Node, &GlobalTransform, &ViewVisibility)>,
+    virtual_cursor: Res<VirtualCursor>,
+    gamepad_focus: Res<GamepadFocus>
+) -> bool {
+    if gamepad_focus.button.is_some() {
+        return true;
+    }
+
+    // bevy only knows where the mouse is
+    if virtual_cursor.active {
+        interaction_q.iter().any(|(_, node, transform, view_visibility)| view_visibility.get() && node_contains(node, transform, virtual_cursor.position))
+    } else {
+        interaction_q.iter().any(|(interaction, ..)| *interaction != Interaction::None)
+    }
+}
+
+pub fn player_end_setup(
+    actions: Actions,
     mut next_game_state: ResMut<NextState<GameState>>,
     placement_state: Res<State<PlacementState>>
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) && placement_state.get().eq(&PlacementState::NEUTRON) {
+    if actions.just_pressed(Action::Start) && placement_state.get().ne(&PlacementState::VELOCITY) {
         next_game_state.set(GameState::GAME);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn player_place_neutrons(
-    window_q: Query<&Window>,
-    camera_q: Query<(&Camera, &GlobalTransform)>,
+    pointer: Pointer,
     atoms_q: Query<(&Transform, &Collider), With<Atom>>,
     mut placement_neutron_q: Query<(Entity, &Transform, &mut Neutron), With<PlacementMarker>>,
+    placement_pointer_q: Query<(Entity, &Parent), With<PlacementPointer>>,
     mut commands: Commands, 
     mut meshes: ResMut<Assets<Mesh>>, 
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mouse_button_input: Res<Input<MouseButton>>,
+    actions: Actions,
     mut level_stats: ResMut<LevelStats>,
     placement_state: Res<State<PlacementState>>,
     mut next_placement_state: ResMut<NextState<PlacementState>>
 ) {
-    if level_stats.num_neutrons <= 0 || !mouse_button_input.just_pressed(MouseButton::Left) {
+    if level_stats.num_neutrons <= 0 
+        || !actions.just_pressed(Action::Place) 
+        || placement_state.get().eq(&PlacementState::ROD) {
         return;
     }
     
-    let window = window_q.single();
-    let (camera, camera_transform) = camera_q.single();
-        
-    if let Some(cursor_pos) = window.cursor_position()
-        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) {
+    if let Some(cursor_pos) = pointer.world_position() {
             
         if placement_state.get().eq(&PlacementState::NEUTRON) {
             for (atom_transform, atom_collider) in &atoms_q {
@@ -115,8 +284,15 @@ pub fn player_place_neutrons(
         } else {
             let (marked_neutron_entity, marked_neutron_transform, mut marked_neutron) = placement_neutron_q.single_mut();
                 
-            commands.entity(marked_neutron_entity).remove::<PlacementMarker>().despawn_descendants();
-            marked_neutron.velocity = clamp_vec2_by_length((cursor_pos - marked_neutron_transform.translation.xy()) * 7.5, 0.1, 1500.);
+            commands.entity(marked_neutron_entity).remove::<PlacementMarker>();
+
+            // just the aiming pointer, the neutron keeps any other children
+            for (pointer_entity, parent) in &placement_pointer_q {
+                if parent.get() == marked_neutron_entity {
+                    commands.entity(pointer_entity).despawn_recursive();
+                }
+            }
+            marked_neutron.velocity = clamp_vec2_by_length((cursor_pos - marked_neutron_transform.translation.xy()) * 7.5, MIN_LAUNCH_SPEED, MAX_LAUNCH_SPEED);
                 
             level_stats.num_neutrons -= 1;
             next_placement_state.set(PlacementState::NEUTRON);
@@ -132,24 +308,19 @@ pub fn player_place_neutrons(
 }
 
 pub fn player_remove_neutron(
-    window_q: Query<&Window>,
-    camera_q: Query<(&Camera, &GlobalTransform)>,
+    pointer: Pointer,
     placement_neutron_q: Query<(Entity, &Transform, &Collider), With<Neutron>>,
     mut commands: Commands, 
-    mouse_button_input: Res<Input<MouseButton>>,
+    actions: Actions,
     mut level_stats: ResMut<LevelStats>,
     placement_state: Res<State<PlacementState>>,
     mut next_placement_state: ResMut<NextState<PlacementState>>
 ) {
-    if !mouse_button_input.just_pressed(MouseButton::Right) {
+    if !actions.just_pressed(Action::Remove) {
         return;
     }
     
-    let window = window_q.single();
-    let (camera, camera_transform) = camera_q.single();
-    
-    if let Some(cursor_pos) = window.cursor_position()
-        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) {
+    if let Some(cursor_pos) = pointer.world_position() {
         
         for (neutron_entity, neutron_transform, neutron_collider) in &placement_neutron_q {
             if !is_cords_in_collider(cursor_pos, neutron_transform.translation.xy(), neutron_collider) {
@@ -168,4 +339,117 @@ pub fn player_remove_neutron(
             return;
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn player_toggle_control_rod_mode(
+    keyboard_input: Res<Input<KeyCode>>,
+    placement_state: Res<State<PlacementState>>,
+    mut next_placement_state: ResMut<NextState<PlacementState>>
+) {
+    if !keyboard_input.just_pressed(KeyCode::R) {
+        return;
+    }
+
+    match placement_state.get() {
+        PlacementState::NEUTRON => next_placement_state.set(PlacementState::ROD),
+        PlacementState::ROD => next_placement_state.set(PlacementState::NEUTRON),
+        // finish aiming the current neutron first
+        PlacementState::VELOCITY => ()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn player_place_control_rods(
+    pointer: Pointer,
+    atoms_q: Query<(&Transform, &Collider), With<Atom>>,
+    mut commands: Commands, 
+    mut meshes: ResMut<Assets<Mesh>>, 
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    actions: Actions,
+    mut level_stats: ResMut<LevelStats>,
+    placement_state: Res<State<PlacementState>>
+) {
+    if level_stats.num_control_rods <= 0 
+        || !actions.just_pressed(Action::Place) 
+        || placement_state.get().ne(&PlacementState::ROD) {
+        return;
+    }
+
+    if let Some(cursor_pos) = pointer.world_position() {
+
+        for (atom_transform, atom_collider) in &atoms_q {
+            if is_circle_in_rect((&atom_transform.translation.xy(), atom_collider), cursor_pos, CONTROL_ROD_SIZE / 2.) {
+                debug!("Control rod at {} would cover a live atom!", cursor_pos);
+                return;
+            }
+        }
+
+        spawn_control_rod(&mut commands, &mut meshes, &mut materials, cursor_pos);
+        level_stats.num_control_rods -= 1;
+
+        debug!("Placed control rod at {}, remaining control rods: {}", cursor_pos, level_stats.num_control_rods);
+    }
+}
+
+pub fn player_remove_control_rod(
+    pointer: Pointer,
+    control_rod_q: Query<(Entity, &Transform, &Absorber), With<ControlRod>>,
+    mut commands: Commands, 
+    actions: Actions,
+    mut level_stats: ResMut<LevelStats>
+) {
+    if !actions.just_pressed(Action::Remove) {
+        return;
+    }
+
+    if let Some(cursor_pos) = pointer.world_position() {
+
+        let cursor_collider = Collider::new(1.);
+
+        for (rod_entity, rod_transform, rod_absorber) in &control_rod_q {
+            if !rod_absorber.is_touching(rod_transform.translation.xy(), (&cursor_pos, &cursor_collider)) {
+                continue;
+            }
+
+            commands.entity(rod_entity).despawn_recursive();
+            level_stats.num_control_rods += 1;
+
+            debug!("Removed control rod at {}, remaining control rods: {}", rod_transform.translation.xy(), level_stats.num_control_rods);
+            return;
+        }
+    }
+}
+
+pub fn player_toggle_pause(
+    actions: Actions,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>
+) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    }
+
+    match game_state.get() {
+        GameState::GAME => next_game_state.set(GameState::PAUSED),
+        GameState::PAUSED => next_game_state.set(GameState::GAME),
+        _ => ()
+    }
+}
+
+pub fn player_change_simulation_speed(
+    actions: Actions,
+    mut game_stats: ResMut<GameStats>
+) {
+    let mut step = 0.;
+
+    if actions.just_pressed(Action::SpeedUp) {
+        step += SIMULATION_SPEED_STEP;
+    }
+    if actions.just_pressed(Action::SpeedDown) {
+        step -= SIMULATION_SPEED_STEP;
+    }
+
+    if step != 0. {
+        game_stats.simulation_speed = clamp(game_stats.simulation_speed + step, MIN_SIMULATION_SPEED, MAX_SIMULATION_SPEED);
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{collision::{collision_listener, CollisionEvent}, level_manager::LevelSetupSet, GameState, GameStats};
+
+/// Simulated seconds that current and peak power are averaged over.
+pub const POWER_WINDOW: f32 = 0.5;
+
+/// What a level grades runs on. `s_score` and the grade thresholds are in the same units.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum ScoreMetric {
+    /// Total energy released, in joules.
+    #[default]
+    Energy,
+    /// The most power over any half second, in watts.
+    PeakPower,
+    /// The most power held over any `seconds` long stretch, in watts. A point on the run's power curve.
+    SustainedPower { seconds: f32 },
+    /// Total energy over the time to completion, in watts, so the same energy released sooner scores higher.
+    AveragePower
+}
+
+/// Energy released over simulated time in the current run.
+#[derive(Resource, Debug, Default)]
+pub struct PowerStats {
+    /// Simulated time and energy of every fission so far, oldest first.
+    pub fissions: Vec<(f32, f64)>,
+    /// Power over the last `POWER_WINDOW`, in watts.
+    pub power: f64,
+    pub peak_power: f64
+}
+
+/// Tracks power as the reaction runs.
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerStats>()
+            .add_systems(OnEnter(GameState::SETUP), power_reset.in_set(LevelSetupSet::Cleanup))
+            .add_systems(Update, power_record.after(collision_listener).run_if(in_state(GameState::GAME)));
+    }
+}
+
+impl ScoreMetric {
+    pub fn value(&self, game_stats: &GameStats, power_stats: &PowerStats) -> f64 {
+        match *self {
+            ScoreMetric::Energy => game_stats.score,
+            ScoreMetric::PeakPower => power_stats.peak_power,
+            ScoreMetric::SustainedPower { seconds } => power_stats.sustained_power(seconds),
+            ScoreMetric::AveragePower if game_stats.simulation_time > 0. => game_stats.score / game_stats.simulation_time as f64,
+            ScoreMetric::AveragePower => 0.
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ScoreMetric::Energy => "J",
+            _ => "W"
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ScoreMetric::Energy => "Energy".to_string(),
+            ScoreMetric::PeakPower => "Peak Power".to_string(),
+            ScoreMetric::SustainedPower { seconds } => format!("Power over {seconds:.1} s"),
+            ScoreMetric::AveragePower => "Average Power".to_string()
+        }
+    }
+}
+
+impl PowerStats {
+    /// The most energy released within any `seconds` long stretch, divided by `seconds`.
+    pub fn sustained_power(&self, seconds: f32) -> f64 {
+        if seconds <= 0. {
+            return 0.;
+        }
+
+        let mut start = 0;
+        let mut energy = 0.;
+        let mut best: f64 = 0.;
+
+        for &(time, fission_energy) in &self.fissions {
+            energy += fission_energy;
+
+            while self.fissions[start].0 <= time - seconds {
+                energy -= self.fissions[start].1;
+                start += 1;
+            }
+
+            best = best.max(energy);
+        }
+
+        best / seconds as f64
+    }
+
+    /// The run's power curve: the most power held for each of `durations`.
+    pub fn power_curve(&self, durations: &[f32]) -> Vec<(f32, f64)> {
+        durations.iter().map(|&seconds| (seconds, self.sustained_power(seconds))).collect()
+    }
+}
+
+pub fn power_reset(
+    mut power_stats: ResMut<PowerStats>
+) {
+    *power_stats = PowerStats::default();
+}
+
+pub fn power_record(
+    mut events: EventReader<CollisionEvent>,
+    game_stats: Res<GameStats>,
+    mut power_stats: ResMut<PowerStats>
+) {
+    let now = game_stats.simulation_time;
+
+    for collision_event in events.read() {
+        power_stats.fissions.push((now, collision_event.energy));
+    }
+
+    // only the newest fissions can be in the window
+    let recent: f64 = power_stats.fissions.iter()
+        .rev()
+        .take_while(|(time, _)| *time > now - POWER_WINDOW)
+        .map(|(_, energy)| energy)
+        .sum();
+
+    power_stats.power = recent / POWER_WINDOW as f64;
+    power_stats.peak_power = power_stats.peak_power.max(power_stats.power);
+}
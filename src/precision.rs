@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+
+use crate::{atom::Atom, collision::{Collider, is_colliding}, hud::Hud, level_manager::LevelStats, neutron::NEUTRON_SIZE, neutron::spawn_neutron, player_controls::{PlacementState, MIN_LAUNCH_SPEED, MAX_LAUNCH_SPEED}, GameState, level_manager::LevelSetupSet};
+
+const FORM_TEXT_SIZE: f32 = 20.;
+/// Length of the preview arrow for a neutron at the fastest launch speed.
+const PREVIEW_LENGTH: f32 = 200.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecisionField {
+    X,
+    Y,
+    Angle,
+    Speed,
+    Grid
+}
+
+/// Exact coordinates and velocity for the next neutron, typed or nudged in from the keyboard.
+#[derive(Resource, Debug)]
+pub struct PrecisionPlacement {
+    pub active: bool,
+    selected: usize,
+    pub position: Vec2,
+    /// Degrees anticlockwise from the x axis.
+    pub angle: f32,
+    pub speed: f32,
+    /// Positions snap to multiples of this, 0 turns snapping off.
+    pub grid: f32,
+    /// What has been typed into the selected field so far.
+    typed: Option<String>
+}
+
+#[derive(Component)]
+pub struct PrecisionForm;
+
+impl Default for PrecisionPlacement {
+    fn default() -> Self {
+        PrecisionPlacement { active: false, selected: 0, position: Vec2::ZERO, angle: 90., speed: 750., grid: 25., typed: None }
+    }
+}
+
+impl PrecisionField {
+    const ALL: [PrecisionField; 5] = [PrecisionField::X, PrecisionField::Y, PrecisionField::Angle, PrecisionField::Speed, PrecisionField::Grid];
+
+    fn label(&self) -> &'static str {
+        match self {
+            PrecisionField::X => "X",
+            PrecisionField::Y => "Y",
+            PrecisionField::Angle => "Angle",
+            PrecisionField::Speed => "Speed",
+            PrecisionField::Grid => "Grid"
+        }
+    }
+}
+
+impl PrecisionPlacement {
+    fn selected_field(&self) -> PrecisionField {
+        PrecisionField::ALL[self.selected]
+    }
+
+    fn value(&self, field: PrecisionField) -> f32 {
+        match field {
+            PrecisionField::X => self.position.x,
+            PrecisionField::Y => self.position.y,
+            PrecisionField::Angle => self.angle,
+            PrecisionField::Speed => self.speed,
+            PrecisionField::Grid => self.grid
+        }
+    }
+
+    fn set_value(&mut self, field: PrecisionField, value: f32) {
+        match field {
+            PrecisionField::X => self.position.x = self.snap(value),
+            PrecisionField::Y => self.position.y = self.snap(value),
+            PrecisionField::Angle => self.angle = value.rem_euclid(360.),
+            PrecisionField::Speed => self.speed = value.clamp(MIN_LAUNCH_SPEED, MAX_LAUNCH_SPEED),
+            PrecisionField::Grid => {
+                self.grid = value.max(0.);
+                self.position = Vec2::new(self.snap(self.position.x), self.snap(self.position.y));
+            }
+        }
+    }
+
+    /// How far one arrow key press moves a field, ten times that with shift held.
+    fn step(&self, field: PrecisionField) -> f32 {
+        match field {
+            PrecisionField::X | PrecisionField::Y if self.grid > 0. => self.grid,
+            PrecisionField::X | PrecisionField::Y => 1.,
+            PrecisionField::Angle => 1.,
+            PrecisionField::Speed => 10.,
+            PrecisionField::Grid => 5.
+        }
+    }
+
+    fn snap(&self, value: f32) -> f32 {
+        if self.grid > 0. {
+            (value / self.grid).round() * self.grid
+        } else {
+            value
+        }
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        Vec2::from_angle(self.angle.to_radians()) * self.speed
+    }
+
+    /// Stores whatever has been typed into the selected field, keeping the old value if it doesn't parse.
+    fn commit_typed(&mut self) {
+        if let Some(value) = self.typed.take().and_then(|typed| typed.parse::<f32>().ok()) {
+            self.set_value(self.selected_field(), value);
+        }
+    }
+}
+
+pub struct PrecisionPlugin;
+
+impl Plugin for PrecisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrecisionPlacement>()
+            .add_systems(OnEnter(GameState::SETUP), precision_setup.in_set(LevelSetupSet::Spawn))
+            .add_systems(Update, (precision_toggle, precision_preview, precision_input.run_if(precision_active)).run_if(in_state(GameState::SETUP)))
+            .add_systems(Update, precision_form_update);
+    }
+}
+
+pub fn precision_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle { font: asset_server.load("fonts/JetBrainsMono-Regular.ttf"), font_size: FORM_TEXT_SIZE, color: Color::ORANGE }
+        ).with_style(
+            Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(5.),
+                top: Val::Px(245.),
+                padding: UiRect::all(Val::Px(6.)),
+                ..default()
+            }
+        ).with_background_color(Color::BLACK.with_a(0.8)),
+        Hud,
+        PrecisionForm
+    ));
+}
+
+pub fn precision_toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut precision_placement: ResMut<PrecisionPlacement>
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        precision_placement.active = !precision_placement.active;
+        precision_placement.typed = None;
+    }
+}
+
+pub fn precision_active(precision_placement: Res<PrecisionPlacement>) -> bool {
+    precision_placement.active
+}
+
+/// Up and down pick a field, left and right nudge it, typing replaces it and enter places the neutron.
+#[allow(clippy::too_many_arguments)]
+pub fn precision_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut received_characters: EventReader<ReceivedCharacter>,
+    mut precision_placement: ResMut<PrecisionPlacement>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    atoms_q: Query<(&Transform, &Collider), With<Atom>>,
+    mut level_stats: ResMut<LevelStats>,
+    placement_state: Res<State<PlacementState>>
+) {
+    for received_character in received_characters.read() {
+        if received_character.char.is_ascii_digit() || matches!(received_character.char, '.' | '-') {
+            precision_placement.typed.get_or_insert_with(String::new).push(received_character.char);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        if let Some(typed) = precision_placement.typed.as_mut() {
+            typed.pop();
+        }
+    }
+
+    let field_count = PrecisionField::ALL.len();
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        precision_placement.commit_typed();
+        precision_placement.selected = (precision_placement.selected + field_count - 1) % field_count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        precision_placement.commit_typed();
+        precision_placement.selected = (precision_placement.selected + 1) % field_count;
+    }
+
+    let mut nudge = 0.;
+
+    if keyboard_input.just_pressed(KeyCode::Left) {
+        nudge -= 1.;
+    }
+    if keyboard_input.just_pressed(KeyCode::Right) {
+        nudge += 1.;
+    }
+
+    if nudge != 0. {
+        precision_placement.commit_typed();
+
+        let field = precision_placement.selected_field();
+        let multiplier = if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) { 10. } else { 1. };
+        let value = precision_placement.value(field) + nudge * precision_placement.step(field) * multiplier;
+
+        precision_placement.set_value(field, value);
+    }
+
+    if !keyboard_input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    precision_placement.commit_typed();
+
+    if level_stats.num_neutrons <= 0 || placement_state.get().ne(&PlacementState::NEUTRON) {
+        return;
+    }
+
+    let position = precision_placement.position;
+    let neutron_collider = Collider::new(NEUTRON_SIZE);
+
+    for (atom_transform, atom_collider) in &atoms_q {
+        if is_colliding((&atom_transform.translation.xy(), atom_collider), (&position, &neutron_collider)) {
+            debug!("Selected position, {}, contains a live atom!", position);
+            return;
+        }
+    }
+
+    let velocity = precision_placement.velocity();
+    spawn_neutron(&mut commands, &mut meshes, &mut materials, position, velocity, 0);
+
+    level_stats.num_neutrons -= 1;
+
+    debug!("Placed neutron at {} with velocity {}, remaining placeable neutrons: {}", position, velocity, level_stats.num_neutrons);
+}
+
+pub fn precision_form_update(
+    mut form_q: Query<(&mut Text, &mut Visibility), With<PrecisionForm>>,
+    precision_placement: Res<PrecisionPlacement>,
+    game_state: Res<State<GameState>>
+) {
+    // neutrons can only be placed during setup
+    let visible = precision_placement.active && game_state.get().eq(&GameState::SETUP);
+
+    for (mut text, mut visibility) in &mut form_q {
+        *visibility = if visible { Visibility::Inherited } else { Visibility::Hidden };
+
+        if !precision_placement.is_changed() && !text.sections[0].value.is_empty() {
+            continue;
+        }
+
+        let mut form = String::from("Precision (Tab to close)");
+
+        for (i, field) in PrecisionField::ALL.iter().enumerate() {
+            let selected = i == precision_placement.selected;
+            let value = match (&precision_placement.typed, selected) {
+                (Some(typed), true) => format!("{typed}_"),
+                _ => format!("{:.1}", precision_placement.value(*field))
+            };
+
+            form.push_str(&format!("\n{} {:<6} {}", if selected { ">" } else { " " }, field.label(), value));
+        }
+
+        form.push_str("\nArrows to nudge, Enter to place");
+        text.sections[0].value = form;
+    }
+}
+
+pub fn precision_preview(
+    mut gizmos: Gizmos,
+    precision_placement: Res<PrecisionPlacement>
+) {
+    if !precision_placement.active {
+        return;
+    }
+
+    let position = precision_placement.position;
+    let direction = Vec2::from_angle(precision_placement.angle.to_radians());
+    let tip = position + direction * PREVIEW_LENGTH * precision_placement.speed / MAX_LAUNCH_SPEED;
+
+    gizmos.circle_2d(position, NEUTRON_SIZE, Color::ORANGE);
+    gizmos.line_2d(position, tip, Color::ORANGE);
+    gizmos.line_2d(tip, tip - Vec2::from_angle(0.5).rotate(direction) * 12., Color::ORANGE);
+    gizmos.line_2d(tip, tip - Vec2::from_angle(-0.5).rotate(direction) * 12., Color::ORANGE);
+}
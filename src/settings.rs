@@ -0,0 +1,318 @@
+use std::{fs, io};
+
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{effects::EffectsOptions, hud::FpsText, menu::{PRESSED_BUTTON, HOVERED_BUTTON, NORMAL_BUTTON}, player_controls::CameraOptions, GameState, GameStats, MIN_SIMULATION_SPEED, MAX_SIMULATION_SPEED, SIMULATION_SPEED_STEP};
+
+const SETTINGS_PATH: &str = "settings.ron";
+const SETTINGS_TEXT_SIZE: f32 = 32.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SettingsWindowMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen
+}
+
+/// Player options, loaded from `settings.ron` at startup and saved when leaving the settings screen.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub zoom_speed: f32,
+    pub movement_speed: f32,
+    /// Simulation speed every level starts at.
+    pub simulation_speed: f32,
+    pub effects_enabled: bool,
+    pub show_fps: bool,
+    pub window_mode: SettingsWindowMode
+}
+
+#[derive(Component)]
+pub struct SettingsMenu;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    ZoomSpeed,
+    MovementSpeed,
+    SimulationSpeed,
+    Effects,
+    ShowFps,
+    WindowMode
+}
+
+#[derive(Component)]
+pub enum SettingsMenuButton {
+    /// Steps a setting down (-1) or up (1).
+    Step { setting: Setting, direction: f32 },
+    Controls,
+    Back
+}
+
+#[derive(Component)]
+pub struct SettingValueText(Setting);
+
+impl Default for Settings {
+    fn default() -> Self {
+        let camera_options = CameraOptions::default();
+
+        Settings {
+            zoom_speed: camera_options.zoom_speed,
+            movement_speed: camera_options.movement_speed,
+            simulation_speed: GameStats::default().simulation_speed,
+            effects_enabled: EffectsOptions::default().enabled,
+            show_fps: true,
+            window_mode: SettingsWindowMode::Windowed
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, falling back to the defaults if it is missing or can't be parsed.
+    pub fn load() -> Self {
+        match fs::read_to_string(SETTINGS_PATH) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+                error!("Failed to parse {}, using default settings: {}", SETTINGS_PATH, error);
+                Settings::default()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(error) => {
+                error!("Failed to read {}, using default settings: {}", SETTINGS_PATH, error);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        fs::write(SETTINGS_PATH, contents)
+    }
+
+    fn step(&mut self, setting: Setting, direction: f32) {
+        match setting {
+            Setting::ZoomSpeed => self.zoom_speed = (self.zoom_speed * 1.25_f32.powf(direction)).clamp(0.01, 1.),
+            Setting::MovementSpeed => self.movement_speed = (self.movement_speed * 1.25_f32.powf(direction)).clamp(10., 2000.),
+            Setting::SimulationSpeed => self.simulation_speed = (self.simulation_speed + SIMULATION_SPEED_STEP * direction).clamp(MIN_SIMULATION_SPEED, MAX_SIMULATION_SPEED),
+            Setting::Effects => self.effects_enabled = !self.effects_enabled,
+            Setting::ShowFps => self.show_fps = !self.show_fps,
+            Setting::WindowMode => self.window_mode = self.window_mode.step(direction)
+        }
+    }
+
+    fn value_text(&self, setting: Setting) -> String {
+        let on_off = |enabled: bool| if enabled { "On" } else { "Off" }.to_string();
+
+        match setting {
+            Setting::ZoomSpeed => format!("{:.3}", self.zoom_speed),
+            Setting::MovementSpeed => format!("{:.0}", self.movement_speed),
+            Setting::SimulationSpeed => format!("{:.2}", self.simulation_speed),
+            Setting::Effects => on_off(self.effects_enabled),
+            Setting::ShowFps => on_off(self.show_fps),
+            Setting::WindowMode => format!("{:?}", self.window_mode)
+        }
+    }
+}
+
+impl SettingsWindowMode {
+    const ALL: [SettingsWindowMode; 3] = [SettingsWindowMode::Windowed, SettingsWindowMode::BorderlessFullscreen, SettingsWindowMode::Fullscreen];
+
+    fn step(&self, direction: f32) -> Self {
+        let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or_default() as i32 + direction as i32;
+        Self::ALL[index.rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+}
+
+impl From<SettingsWindowMode> for WindowMode {
+    fn from(window_mode: SettingsWindowMode) -> Self {
+        match window_mode {
+            SettingsWindowMode::Windowed => WindowMode::Windowed,
+            SettingsWindowMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            SettingsWindowMode::Fullscreen => WindowMode::Fullscreen
+        }
+    }
+}
+
+impl Setting {
+    const ALL: [Setting; 6] = [Setting::ZoomSpeed, Setting::MovementSpeed, Setting::SimulationSpeed, Setting::Effects, Setting::ShowFps, Setting::WindowMode];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Setting::ZoomSpeed => "Zoom Speed",
+            Setting::MovementSpeed => "Camera Speed",
+            Setting::SimulationSpeed => "Default Sim Speed",
+            Setting::Effects => "Fission Effects",
+            Setting::ShowFps => "Show FPS",
+            Setting::WindowMode => "Window Mode"
+        }
+    }
+}
+
+/// Pushes changed settings out to the resources and window they control.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load())
+            .add_systems(OnEnter(GameState::SETTINGS), settings_menu_setup)
+            .add_systems(OnExit(GameState::SETTINGS), settings_menu_cleanup)
+            .add_systems(Update, settings_buttons.run_if(in_state(GameState::SETTINGS)))
+            .add_systems(Update, settings_apply);
+    }
+}
+
+pub fn settings_apply(
+    settings: Res<Settings>,
+    mut camera_options: ResMut<CameraOptions>,
+    mut game_stats: ResMut<GameStats>,
+    mut effects_options: ResMut<EffectsOptions>,
+    mut window_q: Query<&mut Window>,
+    mut fps_q: Query<&mut Visibility, With<FpsText>>
+) {
+    // the fps counter gets respawned with every level
+    for mut fps_visibility in &mut fps_q {
+        fps_visibility.set_if_neq(if settings.show_fps { Visibility::Inherited } else { Visibility::Hidden });
+    }
+
+    if !settings.is_changed() {
+        return;
+    }
+
+    camera_options.zoom_speed = settings.zoom_speed;
+    camera_options.movement_speed = settings.movement_speed;
+    game_stats.simulation_speed = settings.simulation_speed;
+    effects_options.enabled = settings.effects_enabled;
+
+    for mut window in &mut window_q {
+        window.mode = settings.window_mode.into();
+    }
+}
+
+pub fn settings_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>
+) {
+    let font = asset_server.load("fonts/JetBrainsMono-Regular.ttf");
+    let text_style = TextStyle { font: font.clone(), font_size: SETTINGS_TEXT_SIZE, color: Color::ORANGE };
+
+    let button_style = Style {
+        width: Val::Px(50.),
+        height: Val::Px(50.),
+        border: UiRect::all(Val::Px(3.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let root_node = NodeBundle {
+        style: Style {
+            left: Val::Percent(5.),
+            top: Val::Percent(5.),
+            width: Val::Percent(90.),
+            height: Val::Percent(90.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(16.),
+            border: UiRect::all(Val::Px(5.)),
+            ..default()
+        },
+        border_color: Color::ORANGE.into(),
+        ..default()
+    };
+
+    commands.spawn((root_node, SettingsMenu))
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Settings", TextStyle { font_size: 72., ..text_style.clone() }));
+
+        for setting in Setting::ALL {
+            parent.spawn(NodeBundle {
+                style: Style { align_items: AlignItems::Center, column_gap: Val::Px(16.), ..default() },
+                ..default()
+            }).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(setting.label(), text_style.clone()).with_style(Style { width: Val::Px(400.), ..default() }));
+
+                for (direction, arrow) in [(-1., "<"), (1., ">")] {
+                    if direction > 0. {
+                        parent.spawn((
+                            TextBundle::from_section(settings.value_text(setting), text_style.clone())
+                                .with_style(Style { width: Val::Px(300.), ..default() })
+                                .with_text_alignment(TextAlignment::Center),
+                            SettingValueText(setting)
+                        ));
+                    }
+
+                    parent.spawn((
+                        ButtonBundle { style: button_style.clone(), border_color: Color::ORANGE.into(), ..default() },
+                        SettingsMenuButton::Step { setting, direction }
+                    )).with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(arrow, text_style.clone()));
+                    });
+                }
+            });
+        }
+
+        parent.spawn(NodeBundle {
+            style: Style { column_gap: Val::Px(16.), ..default() },
+            ..default()
+        }).with_children(|parent| {
+            for (settings_menu_button, label) in [(SettingsMenuButton::Controls, "Controls"), (SettingsMenuButton::Back, "Back")] {
+                parent.spawn((
+                    ButtonBundle { style: Style { width: Val::Px(200.), ..button_style.clone() }, border_color: Color::ORANGE.into(), ..default() },
+                    settings_menu_button
+                )).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                });
+            }
+        });
+    });
+}
+
+pub fn settings_menu_cleanup(
+    mut commands: Commands,
+    settings_menu_q: Query<Entity, With<SettingsMenu>>,
+    settings: Res<Settings>
+) {
+    settings_menu_q.for_each(|settings_menu_entity| commands.entity(settings_menu_entity).despawn_recursive());
+
+    match settings.save() {
+        Ok(()) => info!("Saved settings to {}", SETTINGS_PATH),
+        Err(error) => error!("Failed to save settings to {}: {}", SETTINGS_PATH, error)
+    }
+}
+
+pub fn settings_buttons(
+    mut interaction_q: Query<(&Interaction, &mut BackgroundColor, &SettingsMenuButton), Changed<Interaction>>,
+    mut value_text_q: Query<(&mut Text, &SettingValueText)>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    for (interaction, mut bg_color, settings_menu_button) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *bg_color = PRESSED_BUTTON.into();
+
+                match settings_menu_button {
+                    SettingsMenuButton::Step { setting, direction } => settings.step(*setting, *direction),
+                    SettingsMenuButton::Controls => next_state.set(GameState::CONTROLS),
+                    SettingsMenuButton::Back => next_state.set(GameState::MENU)
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = HOVERED_BUTTON.into();
+            },
+            Interaction::None => {
+                *bg_color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+
+    if settings.is_changed() {
+        for (mut text, value_text) in &mut value_text_q {
+            text.sections[0].value = settings.value_text(value_text.0);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::{fs::{self, File}, io::{self, Write, BufWriter}, path::PathBuf, str::FromStr, time::{SystemTime, UNIX_EPOCH}};
+
+use bevy::prelude::*;
+
+use crate::{collision::CollisionEvent, neutron::Neutron, GameStats, level_manager::LevelSetupSet, GameState};
+
+const TELEMETRY_DIRECTORY: &str = "telemetry";
+const CSV_HEADER: &str = "record,time,x,y,vx,vy,neutrons_emitted,energy,generation,active_neutrons,fissions,total_energy";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TelemetryFormat {
+    #[default]
+    Csv,
+    JsonLines
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TelemetryOptions {
+    pub enabled: bool,
+    pub format: TelemetryFormat
+}
+
+/// The file the current run is being written to.
+#[derive(Resource)]
+pub struct TelemetryLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    format: TelemetryFormat
+}
+
+impl TelemetryFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            TelemetryFormat::Csv => "csv",
+            TelemetryFormat::JsonLines => "jsonl"
+        }
+    }
+}
+
+impl FromStr for TelemetryFormat {
+    type Err = ();
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "csv" => Ok(TelemetryFormat::Csv),
+            "jsonl" => Ok(TelemetryFormat::JsonLines),
+            _ => Err(())
+        }
+    }
+}
+
+impl TelemetryLog {
+    fn create(format: TelemetryFormat) -> io::Result<Self> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis()).unwrap_or_default();
+        let path = PathBuf::from(TELEMETRY_DIRECTORY).join(format!("run-{timestamp}.{}", format.extension()));
+
+        fs::create_dir_all(TELEMETRY_DIRECTORY)?;
+        let mut writer = BufWriter::new(File::create(&path)?);
+
+        if format == TelemetryFormat::Csv {
+            writeln!(writer, "{CSV_HEADER}")?;
+        }
+
+        Ok(TelemetryLog { path, writer, format })
+    }
+
+    fn write_fission(&mut self, time: f32, collision_event: &CollisionEvent) -> io::Result<()> {
+        let position = collision_event.atom_position;
+        let velocity = collision_event.neutron_velocity;
+
+        match self.format {
+            TelemetryFormat::Csv => writeln!(
+                self.writer,
+                "fission,{time},{},{},{},{},{},{:e},{},,,",
+                position.x, position.y, velocity.x, velocity.y, collision_event.num_neutrons, collision_event.energy, collision_event.neutron_generation
+            ),
+            TelemetryFormat::JsonLines => writeln!(
+                self.writer,
+                r#"{{"record":"fission","time":{time},"x":{},"y":{},"vx":{},"vy":{},"neutrons_emitted":{},"energy":{:e},"generation":{}}}"#,
+                position.x, position.y, velocity.x, velocity.y, collision_event.num_neutrons, collision_event.energy, collision_event.neutron_generation
+            )
+        }
+    }
+
+    fn write_tick(&mut self, time: f32, active_neutrons: usize, fissions: usize, total_energy: f64) -> io::Result<()> {
+        match self.format {
+            TelemetryFormat::Csv => writeln!(self.writer, "tick,{time},,,,,,,,{active_neutrons},{fissions},{total_energy:e}"),
+            TelemetryFormat::JsonLines => writeln!(
+                self.writer,
+                r#"{{"record":"tick","time":{time},"active_neutrons":{active_neutrons},"fissions":{fissions},"total_energy":{total_energy:e}}}"#
+            )
+        }
+    }
+}
+
+/// Off unless a `TelemetryOptions` with `enabled` set is inserted after it.
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TelemetryOptions>()
+            .add_systems(OnEnter(GameState::SETUP), telemetry_finish.in_set(LevelSetupSet::Cleanup))
+            .add_systems(OnTransition { from: GameState::SETUP, to: GameState::GAME }, telemetry_start)
+            .add_systems(OnEnter(GameState::RESULTS), telemetry_finish)
+            .add_systems(Update, telemetry_record.run_if(in_state(GameState::GAME)));
+    }
+}
+
+pub fn telemetry_start(
+    mut commands: Commands,
+    telemetry_options: Res<TelemetryOptions>
+) {
+    if !telemetry_options.enabled {
+        return;
+    }
+
+    match TelemetryLog::create(telemetry_options.format) {
+        Ok(telemetry_log) => commands.insert_resource(telemetry_log),
+        Err(error) => error!("Failed to create telemetry file: {}", error)
+    }
+}
+
+pub fn telemetry_record(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    neutron_q: Query<(), With<Neutron>>,
+    game_stats: Res<GameStats>,
+    telemetry_log: Option<ResMut<TelemetryLog>>
+) {
+    let Some(mut telemetry_log) = telemetry_log else {
+        events.clear();
+        return;
+    };
+
+    let time = game_stats.simulation_time;
+    let mut fissions = 0;
+
+    let result = events.read().try_for_each(|collision_event| {
+        fissions += 1;
+        telemetry_log.write_fission(time, collision_event)
+    }).and_then(|_| telemetry_log.write_tick(time, neutron_q.iter().count(), fissions, game_stats.score));
+
+    if let Err(error) = result {
+        error!("Failed to write telemetry to {}, stopping: {}", telemetry_log.path.display(), error);
+        commands.remove_resource::<TelemetryLog>();
+    }
+}
+
+pub fn telemetry_finish(
+    mut commands: Commands,
+    telemetry_log: Option<ResMut<TelemetryLog>>
+) {
+    let Some(mut telemetry_log) = telemetry_log else {
+        return;
+    };
+
+    match telemetry_log.writer.flush() {
+        Ok(()) => info!("Telemetry for this run written to {}", telemetry_log.path.display()),
+        Err(error) => error!("Failed to finish telemetry file {}: {}", telemetry_log.path.display(), error)
+    }
+
+    commands.remove_resource::<TelemetryLog>();
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::{neutron::Neutron, GameState};
+
+/// Past positions kept per neutron.
+const TRAIL_LENGTH: usize = 24;
+/// Speed drawn fully red when colouring by speed, matching the fastest a player can launch a neutron.
+const TRAIL_MAX_SPEED: f32 = 1500.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailColorMode {
+    #[default]
+    Generation,
+    Speed
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TrailOptions {
+    pub enabled: bool,
+    pub color_mode: TrailColorMode
+}
+
+/// Ring buffer of a neutron's most recent positions.
+#[derive(Component, Debug)]
+pub struct Trail {
+    positions: [Vec2; TRAIL_LENGTH],
+    head: usize,
+    len: usize
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Trail { positions: [Vec2::ZERO; TRAIL_LENGTH], head: 0, len: 0 }
+    }
+}
+
+impl Trail {
+    pub fn push(&mut self, position: Vec2) {
+        self.positions[self.head] = position;
+        self.head = (self.head + 1) % TRAIL_LENGTH;
+        self.len = (self.len + 1).min(TRAIL_LENGTH);
+    }
+
+    /// Positions from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = Vec2> + '_ {
+        let start = (self.head + TRAIL_LENGTH - self.len) % TRAIL_LENGTH;
+        (0..self.len).map(move |i| self.positions[(start + i) % TRAIL_LENGTH])
+    }
+}
+
+impl TrailColorMode {
+    pub fn next(&self) -> Self {
+        match self {
+            TrailColorMode::Generation => TrailColorMode::Speed,
+            TrailColorMode::Speed => TrailColorMode::Generation
+        }
+    }
+
+    fn color(&self, neutron: &Neutron) -> Color {
+        match self {
+            TrailColorMode::Generation => Color::hsl((200. + neutron.generation as f32 * 47.) % 360., 0.8, 0.6),
+            TrailColorMode::Speed => {
+                let t = (neutron.velocity.length() / TRAIL_MAX_SPEED).min(1.);
+                Color::rgb(t, 0.3, 1. - t)
+            }
+        }
+    }
+}
+
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrailOptions>()
+            .add_systems(Update, (trail_attach, trail_record).run_if(in_state(GameState::GAME)))
+            .add_systems(Update, (trail_draw, trail_cleanup.run_if(trails_disabled)));
+    }
+}
+
+pub fn trail_attach(
+    mut commands: Commands,
+    neutron_q: Query<Entity, (With<Neutron>, Without<Trail>)>,
+    trail_options: Res<TrailOptions>
+) {
+    if !trail_options.enabled {
+        return;
+    }
+
+    for neutron_entity in &neutron_q {
+        commands.entity(neutron_entity).insert(Trail::default());
+    }
+}
+
+pub fn trail_record(
+    mut neutron_q: Query<(&Transform, &mut Trail), With<Neutron>>,
+    trail_options: Res<TrailOptions>
+) {
+    if !trail_options.enabled {
+        return;
+    }
+
+    neutron_q.par_iter_mut().for_each(|(neutron_transform, mut trail)| trail.push(neutron_transform.translation.xy()));
+}
+
+pub fn trail_draw(
+    mut gizmos: Gizmos,
+    neutron_q: Query<(&Neutron, &Trail)>,
+    trail_options: Res<TrailOptions>
+) {
+    if !trail_options.enabled {
+        return;
+    }
+
+    for (neutron, trail) in &neutron_q {
+        let color = trail_options.color_mode.color(neutron);
+        let len = trail.len as f32;
+
+        gizmos.linestrip_gradient_2d(
+            trail.iter().enumerate().map(|(i, position)| (position, color.with_a((i + 1) as f32 / len)))
+        );
+    }
+}
+
+pub fn trails_disabled(trail_options: Res<TrailOptions>) -> bool {
+    trail_options.is_changed() && !trail_options.enabled
+}
+
+pub fn trail_cleanup(
+    mut commands: Commands,
+    trail_q: Query<Entity, With<Trail>>
+) {
+    trail_q.for_each(|trail_entity| { commands.entity(trail_entity).remove::<Trail>(); });
+}
@@ -0,0 +1,147 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use serde::Deserialize;
+
+use crate::collision::{closest_point_on_segment, reflect_velocity};
+
+pub const WALL_THICKNESS: f32 = 8.;
+const WALL_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+const BOUNDARY_CIRCLE_SEGMENTS: usize = 64;
+
+/// A straight wall segment that bounces neutrons back. A restitution of 1 keeps all of the
+/// neutron's speed along the wall normal, 0 kills it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Wall {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub restitution: f32
+}
+
+/// A closed shape around the whole level, turned into walls facing inwards when the level loads.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Boundary {
+    Circle { radius: f32, restitution: f32 },
+    Polygon { points: Vec<Vec2>, restitution: f32 }
+}
+
+impl Wall {
+    pub fn new(start: Vec2, end: Vec2, restitution: f32) -> Self {
+        Wall { start, end, restitution }
+    }
+
+    /// If a circle at `position` overlaps the wall, returns the unit normal pointing from the wall to the circle.
+    pub fn contact_normal(&self, position: Vec2, radius: f32) -> Option<Vec2> {
+        let closest_point = closest_point_on_segment(position, self.start, self.end);
+        let to_circle = position - closest_point;
+
+        if to_circle.length_squared() >= (radius + WALL_THICKNESS / 2.).powi(2) {
+            return None;
+        }
+
+        // dead centre on the wall, pick the side using the segment's own normal
+        Some(to_circle.try_normalize().unwrap_or_else(|| (self.end - self.start).perp().normalize_or_zero()))
+    }
+}
+
+impl Boundary {
+    pub fn rectangle(size: Vec2, restitution: f32) -> Self {
+        let half_size = size / 2.;
+
+        Boundary::Polygon {
+            points: vec![
+                Vec2::new(-half_size.x, -half_size.y), Vec2::new(half_size.x, -half_size.y),
+                Vec2::new(half_size.x, half_size.y), Vec2::new(-half_size.x, half_size.y)
+            ],
+            restitution
+        }
+    }
+
+    pub fn walls(&self) -> Vec<Wall> {
+        match self {
+            Boundary::Circle { radius, restitution } => {
+                let points: Vec<Vec2> = (0..BOUNDARY_CIRCLE_SEGMENTS)
+                    .map(|i| Vec2::from_angle(TAU * i as f32 / BOUNDARY_CIRCLE_SEGMENTS as f32) * *radius)
+                    .collect();
+
+                polygon_walls(&points, *restitution)
+            },
+            Boundary::Polygon { points, restitution } => polygon_walls(points, *restitution)
+        }
+    }
+}
+
+fn polygon_walls(points: &[Vec2], restitution: f32) -> Vec<Wall> {
+    (0..points.len())
+        .map(|i| Wall::new(points[i], points[(i + 1) % points.len()], restitution))
+        .collect()
+}
+
+/// Moves a neutron by `displacement`, bouncing it off any walls it runs into on the way.
+/// The move is split into steps no longer than the neutron's radius so fast neutrons can't tunnel through.
+pub fn move_with_walls(
+    position: &mut Vec2,
+    velocity: &mut Vec2,
+    displacement: Vec2,
+    radius: f32,
+    walls: &[Wall]
+) {
+    let steps = (displacement.length() / radius).ceil().max(1.) as usize;
+    let mut step_length = displacement.length() / steps as f32;
+
+    for _ in 0..steps {
+        // a wall with no restitution can stop a neutron dead
+        if velocity.length_squared() < f32::EPSILON {
+            return;
+        }
+
+        *position += velocity.normalize() * step_length;
+
+        for wall in walls {
+            let Some(normal) = wall.contact_normal(*position, radius) else {
+                continue;
+            };
+
+            if velocity.dot(normal) >= 0. {
+                continue;
+            }
+
+            let old_speed = velocity.length();
+            *velocity = reflect_velocity(*velocity, normal, wall.restitution);
+            step_length *= velocity.length() / old_speed;
+
+            let closest_point = closest_point_on_segment(*position, wall.start, wall.end);
+            *position = closest_point + normal * (radius + WALL_THICKNESS / 2.);
+        }
+    }
+}
+
+pub fn spawn_wall(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    wall: Wall
+) {
+    let along = wall.end - wall.start;
+
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Quad { size: Vec2::new(along.length() + WALL_THICKNESS, WALL_THICKNESS), flip: false }.into()).into(),
+            material: materials.add(ColorMaterial::from(WALL_COLOR)),
+            transform: Transform {
+                translation: Vec3::from(((wall.start + wall.end) / 2., 1.5)),
+                rotation: Quat::from_rotation_z(along.y.atan2(along.x)),
+                ..default()
+            },
+            ..default()
+        },
+        wall
+    ));
+}
+
+pub fn wall_cleanup(
+    mut commands: Commands,
+    wall_q: Query<Entity, With<Wall>>
+) {
+    wall_q.for_each(|wall_entity| commands.entity(wall_entity).despawn_recursive());
+}
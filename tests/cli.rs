@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use nucleus::{absorber::ControlRod, cli::LaunchOptions, headless_app, level_manager::LevelStats, neutron::Neutron, telemetry::TelemetryFormat, GameState};
+
+fn parse(args: &[&str]) -> Result<LaunchOptions, String> {
+    LaunchOptions::from_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn telemetry_format_is_optional() {
+    assert_eq!(parse(&[]).unwrap().telemetry, None);
+    assert_eq!(parse(&["--telemetry"]).unwrap().telemetry, Some(TelemetryFormat::Csv));
+    assert_eq!(parse(&["--telemetry=jsonl"]).unwrap().telemetry, Some(TelemetryFormat::JsonLines));
+    assert_eq!(parse(&["--telemetry", "jsonl"]).unwrap().telemetry, Some(TelemetryFormat::JsonLines));
+}
+
+#[test]
+fn telemetry_leaves_the_next_flag_alone() {
+    let launch_options = parse(&["--telemetry", "--level", "1"]).unwrap();
+
+    assert_eq!(launch_options.telemetry, Some(TelemetryFormat::Csv));
+    assert_eq!(launch_options.level, Some(1));
+}
+
+#[test]
+fn unknown_telemetry_formats_are_errors() {
+    assert!(parse(&["--telemetry=xml"]).is_err());
+    assert!(parse(&["--telemetry", "xml"]).is_err());
+}
+
+#[test]
+fn placements_start_the_run_on_launch() {
+    let launch_options = parse(&["--level-file", "levels/example.ron", "--placements", "levels/example-placements.ron"]).unwrap();
+    let mut app = headless_app();
+
+    app.add_plugins(launch_options);
+
+    for _ in 0..3 {
+        app.update();
+    }
+
+    assert_eq!(*app.world.resource::<State<GameState>>().get(), GameState::GAME);
+    assert_eq!(app.world.query_filtered::<(), With<Neutron>>().iter(&app.world).count(), 1);
+    assert_eq!(app.world.query_filtered::<(), With<ControlRod>>().iter(&app.world).count(), 1);
+
+    // counted against the level's own limits, not overwritten by them
+    let level_stats = app.world.resource::<LevelStats>();
+    assert_eq!((level_stats.num_neutrons, level_stats.num_control_rods), (0, 0));
+}
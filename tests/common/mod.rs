@@ -0,0 +1,100 @@
+//! A headless app running just the simulation, for driving levels from tests.
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use nucleus::{atom::Atom, cli::{launch_placements, PlacedNeutron, Placements}, collision::Collider, headless_app, level_manager::{Level, LevelFile, LevelStats}, neutron::Neutron, GameState, GameStats};
+
+/// Sets up a built in level and waits in `GameState::SETUP`.
+pub fn load_level(level: i32) -> App {
+    let mut app = headless_app();
+
+    app.world.resource_mut::<GameStats>().level = level;
+    enter_setup(&mut app);
+
+    app
+}
+
+/// The contents of one of the files in `levels/`.
+pub fn level_file(name: &str) -> String {
+    std::fs::read_to_string(format!("{}/levels/{name}", env!("CARGO_MANIFEST_DIR"))).expect("level file should exist")
+}
+
+/// Sets up a level from RON, the same as `--level-file` would.
+pub fn load_level_file(level: &str) -> App {
+    load_level_value(ron::from_str(level).expect("level should parse"))
+}
+
+/// Sets up a level built in code, such as a generated endless one.
+pub fn load_level_value(level: Level) -> App {
+    let mut app = headless_app();
+
+    app.insert_resource(LevelFile(level));
+    enter_setup(&mut app);
+
+    app
+}
+
+/// Places the neutrons and starts the run, the same as `--placements` would.
+pub fn start_run(app: &mut App, neutrons: Vec<PlacedNeutron>) {
+    app.insert_resource(Placements { neutrons, control_rods: Vec::new() });
+    app.world.run_system_once(launch_placements);
+
+    // the placements ask for the run to start, which happens on the next update
+    app.update();
+    assert_eq!(state(app), GameState::GAME);
+}
+
+fn enter_setup(app: &mut App) {
+    app.insert_resource(NextState(Some(GameState::SETUP)));
+    app.update();
+    assert_eq!(state(app), GameState::SETUP);
+}
+
+pub fn step(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+/// Steps until the reaction dies out, returning how many ticks it took, or `None` if it is still going after `max_ticks`.
+pub fn run_until_finished(app: &mut App, max_ticks: usize) -> Option<usize> {
+    for tick in 1..=max_ticks {
+        app.update();
+
+        if state(app) == GameState::RESULTS {
+            return Some(tick);
+        }
+    }
+
+    None
+}
+
+pub fn state(app: &App) -> GameState {
+    app.world.resource::<State<GameState>>().get().clone()
+}
+
+pub fn score(app: &App) -> f64 {
+    app.world.resource::<GameStats>().score
+}
+
+pub fn level_stats(app: &App) -> &LevelStats {
+    app.world.resource::<LevelStats>()
+}
+
+pub fn atom_count(app: &mut App) -> usize {
+    app.world.query_filtered::<(), With<Atom>>().iter(&app.world).count()
+}
+
+pub fn neutron_velocities(app: &mut App) -> Vec<Vec2> {
+    app.world.query::<&Neutron>().iter(&app.world).map(|neutron| neutron.velocity).collect()
+}
+
+/// A neutron just below the lowest atom, heading straight up into it.
+pub fn shot_at_lowest_atom(app: &mut App, speed: f32) -> PlacedNeutron {
+    let (position, radius) = app.world.query_filtered::<(&Transform, &Collider), With<Atom>>()
+        .iter(&app.world)
+        .map(|(transform, collider)| (transform.translation.xy(), collider.radius))
+        .min_by(|(a, _), (b, _)| a.y.total_cmp(&b.y))
+        .expect("level should have atoms");
+
+    PlacedNeutron { position: position - Vec2::Y * (radius * 2. + 20.), velocity: Vec2::Y * speed }
+}
@@ -0,0 +1,166 @@
+use nucleus::{level_manager::{calculate_grade, LevelStats}, power::{PowerStats, ScoreMetric}, GameStats, objective::{AtomCounts, Objective, ObjectiveProgress, ObjectiveStatus, Objectives}};
+
+const S_SCORE: f64 = 1.;
+
+fn level_stats() -> LevelStats {
+    LevelStats::new(1, 0, S_SCORE)
+}
+
+fn grade(score: f64) -> String {
+    calculate_grade(score, &level_stats()).to_string()
+}
+
+#[test]
+fn grades_at_each_threshold() {
+    // each letter i needs half the target plus 1/(2 + i) of it
+    let thresholds = [("S", 0.5 + 1./2.), ("A", 0.5 + 1./3.), ("B", 0.5 + 1./4.), ("C", 0.5 + 1./5.), ("D", 0.5 + 1./6.)];
+
+    for (letter, threshold) in thresholds {
+        assert_eq!(grade(threshold * S_SCORE), letter, "at the {letter} threshold");
+        assert_ne!(grade(threshold * S_SCORE - 1e-3), letter, "just below the {letter} threshold");
+    }
+}
+
+#[test]
+fn grades_below_and_above_the_range() {
+    assert_eq!(grade(0.), "F");
+    assert_eq!(grade(0.6), "F");
+    assert_eq!(grade(10.), "S");
+}
+
+#[test]
+fn float_error_does_not_drop_a_grade() {
+    // three fissions summed one at a time land a hair away from 3 × the energy
+    let energy = 3.2e-11;
+    let score = energy + energy + energy;
+
+    assert_eq!(calculate_grade(score, &LevelStats::new(1, 0, energy * 3.)), "S");
+}
+
+const NO_ATOMS: AtomCounts = AtomCounts { targets: 0, protected: 0, untagged: 0 };
+const LEVEL_ATOMS: AtomCounts = AtomCounts { targets: 2, protected: 1, untagged: 3 };
+
+fn progress(remaining: AtomCounts, score: f64, simulation_time: f32, finished: bool) -> ObjectiveProgress {
+    ObjectiveProgress { score, simulation_time, total: LEVEL_ATOMS, remaining, finished }
+}
+
+#[test]
+fn objectives_settle_when_the_reaction_ends() {
+    let untouched = progress(LEVEL_ATOMS, 0., 1., false);
+    let finished = progress(LEVEL_ATOMS, 0., 1., true);
+
+    for objective in [Objective::FissionTargets { only: false }, Objective::EnergyWithin { energy: 1., seconds: 5. }, Objective::ClearAll, Objective::SustainFor { seconds: 2. }] {
+        assert_eq!(objective.evaluate(ObjectiveStatus::Pending, &untouched), ObjectiveStatus::Pending, "{objective:?} mid run");
+        assert_eq!(objective.evaluate(ObjectiveStatus::Pending, &finished), ObjectiveStatus::Failed, "{objective:?} once over");
+    }
+
+    assert_eq!(Objective::SpareProtected.evaluate(ObjectiveStatus::Pending, &untouched), ObjectiveStatus::Pending);
+    assert_eq!(Objective::SpareProtected.evaluate(ObjectiveStatus::Pending, &finished), ObjectiveStatus::Complete);
+}
+
+#[test]
+fn splitting_a_protected_atom_fails_for_good() {
+    let split = progress(AtomCounts { protected: 0, ..LEVEL_ATOMS }, 0., 1., false);
+
+    assert_eq!(Objective::SpareProtected.evaluate(ObjectiveStatus::Pending, &split), ObjectiveStatus::Failed);
+    assert_eq!(Objective::SpareProtected.evaluate(ObjectiveStatus::Failed, &progress(LEVEL_ATOMS, 0., 1., true)), ObjectiveStatus::Failed);
+}
+
+#[test]
+fn only_targets_is_spoiled_by_anything_else() {
+    let only_targets = Objective::FissionTargets { only: true };
+    let targets_split = progress(AtomCounts { targets: 0, ..LEVEL_ATOMS }, 0., 1., false);
+    let then_another = progress(AtomCounts { targets: 0, untagged: 2, ..LEVEL_ATOMS }, 0., 1., false);
+
+    assert_eq!(only_targets.evaluate(ObjectiveStatus::Pending, &targets_split), ObjectiveStatus::Complete);
+    assert_eq!(only_targets.evaluate(ObjectiveStatus::Complete, &then_another), ObjectiveStatus::Failed);
+    assert_eq!(Objective::FissionTargets { only: false }.evaluate(ObjectiveStatus::Complete, &then_another), ObjectiveStatus::Complete);
+}
+
+#[test]
+fn timed_objectives() {
+    let energy_within = Objective::EnergyWithin { energy: 1., seconds: 5. };
+
+    assert_eq!(energy_within.evaluate(ObjectiveStatus::Pending, &progress(LEVEL_ATOMS, 1., 4., false)), ObjectiveStatus::Complete);
+    assert_eq!(energy_within.evaluate(ObjectiveStatus::Pending, &progress(LEVEL_ATOMS, 1., 6., false)), ObjectiveStatus::Failed);
+    // reaching it in time counts even once the time is up
+    assert_eq!(energy_within.evaluate(ObjectiveStatus::Complete, &progress(LEVEL_ATOMS, 1., 6., true)), ObjectiveStatus::Complete);
+
+    let sustain = Objective::SustainFor { seconds: 2. };
+
+    assert_eq!(sustain.evaluate(ObjectiveStatus::Pending, &progress(LEVEL_ATOMS, 0., 2.5, true)), ObjectiveStatus::Complete);
+    assert_eq!(Objective::ClearAll.evaluate(ObjectiveStatus::Pending, &progress(NO_ATOMS, 0., 1., false)), ObjectiveStatus::Complete);
+}
+
+#[test]
+fn a_failed_objective_grades_f() {
+    let level_stats = level_stats();
+    let mut objectives = Objectives { objectives: vec![(Objective::SpareProtected, ObjectiveStatus::Pending)], total: LEVEL_ATOMS };
+
+    assert_eq!(objectives.grade(10., &level_stats), "S");
+
+    objectives.update(&progress(NO_ATOMS, 10., 1., false));
+    assert_eq!(objectives.grade(10., &level_stats), "F");
+}
+
+#[test]
+fn levels_can_set_their_own_grades() {
+    // listed out of order on purpose, mixing joules with percentages of the S score
+    let level_stats: LevelStats = ron::from_str("(
+        num_neutrons: 1,
+        num_control_rods: 0,
+        s_score: 1.,
+        grades: [(letter: \"A\", at: Percent(50.)), (letter: \"S+\", at: Value(1.5)), (letter: \"S\", at: Percent(100.))],
+    )").unwrap();
+
+    assert_eq!(calculate_grade(2., &level_stats), "S+");
+    assert_eq!(calculate_grade(1.5, &level_stats), "S+");
+    assert_eq!(calculate_grade(1.2, &level_stats), "S");
+    assert_eq!(calculate_grade(0.5, &level_stats), "A");
+    assert_eq!(calculate_grade(0.4, &level_stats), "F");
+}
+
+#[test]
+fn next_grade_says_how_far_off_it_is() {
+    let level_stats = level_stats();
+
+    let (letter, needed) = level_stats.next_grade(0.).unwrap();
+    assert_eq!(letter, "D");
+    assert!((needed - (0.5 + 1./6.) * S_SCORE).abs() < 1e-9);
+
+    let (letter, needed) = level_stats.next_grade(0.8).unwrap();
+    assert_eq!(letter, "A");
+    assert!((needed - (0.5 + 1./3. - 0.8) * S_SCORE).abs() < 1e-9);
+
+    assert!(level_stats.next_grade(S_SCORE).is_none(), "nothing above S");
+}
+
+fn with_fissions(fissions: &[(f32, f64)]) -> PowerStats {
+    PowerStats { fissions: fissions.to_vec(), ..PowerStats::default() }
+}
+
+#[test]
+fn sustained_power_finds_the_busiest_stretch() {
+    // a slow start, then three fissions close together
+    let power_stats = with_fissions(&[(0., 1.), (2., 1.), (4., 1.), (4.2, 1.), (4.4, 1.)]);
+
+    assert!((power_stats.sustained_power(0.5) - 3. / 0.5).abs() < 1e-9);
+    assert!((power_stats.sustained_power(2.) - 3. / 2.).abs() < 1e-9);
+    assert!((power_stats.sustained_power(10.) - 5. / 10.).abs() < 1e-9);
+    assert_eq!(with_fissions(&[]).sustained_power(1.), 0.);
+}
+
+#[test]
+fn metrics_grade_on_their_own_values() {
+    let game_stats = GameStats { score: 4., simulation_time: 2., ..GameStats::default() };
+    let power_stats = PowerStats { peak_power: 6., ..with_fissions(&[(0., 2.), (1., 1.), (1.5, 1.)]) };
+
+    assert_eq!(ScoreMetric::Energy.value(&game_stats, &power_stats), 4.);
+    assert_eq!(ScoreMetric::PeakPower.value(&game_stats, &power_stats), 6.);
+    assert_eq!(ScoreMetric::SustainedPower { seconds: 1. }.value(&game_stats, &power_stats), 2.);
+    assert_eq!(ScoreMetric::AveragePower.value(&game_stats, &power_stats), 2.);
+
+    // the same energy released in half the time
+    let faster = GameStats { simulation_time: 1., ..game_stats };
+    assert_eq!(ScoreMetric::AveragePower.value(&faster, &power_stats), 4.);
+}
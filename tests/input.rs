@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use nucleus::input::{Action, Binding, InputBindings};
+
+#[test]
+fn binding_a_used_key_swaps_the_two_actions() {
+    let mut input_bindings = InputBindings::default();
+    let zoom_out = input_bindings.get(Action::ZoomOut);
+
+    assert_eq!(input_bindings.bind(Action::ZoomOut, Binding::Key(KeyCode::E)), Ok(Some(Action::ZoomIn)));
+    assert_eq!(input_bindings.get(Action::ZoomOut), Some(Binding::Key(KeyCode::E)));
+    assert_eq!(input_bindings.get(Action::ZoomIn), zoom_out);
+
+    assert_eq!(input_bindings.bind(Action::Pause, Binding::Key(KeyCode::K)), Ok(None));
+}
+
+#[test]
+fn reserved_keys_cannot_be_bound() {
+    let mut input_bindings = InputBindings::default();
+
+    assert!(input_bindings.bind(Action::Start, Binding::Key(KeyCode::R)).is_err());
+    assert_eq!(input_bindings.get(Action::Start), Some(Binding::Key(KeyCode::Space)));
+}
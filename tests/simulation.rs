@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use nucleus::{cli::PlacedNeutron, endless::{generate_endless_level, search_s_score}, level_manager::{calculate_grade, get_level}, objective::{AtomCounts, ObjectiveStatus, Objectives}, power::{PowerStats, POWER_WINDOW}, GameState, ENERGY_RELEASED};
+
+mod common;
+
+use common::*;
+
+const LAUNCH_SPEED: f32 = 750.;
+
+fn levels() -> impl Iterator<Item = i32> {
+    (0..).take_while(|level| get_level(*level).is_some())
+}
+
+#[test]
+fn every_level_sets_up() {
+    for level in levels() {
+        let mut app = load_level(level);
+        let level_stats = level_stats(&app);
+
+        assert!(level_stats.num_neutrons > 0, "level {level} gives no neutrons");
+        assert!(level_stats.s_score > 0., "level {level} has no target score");
+        assert!(atom_count(&mut app) > 0, "level {level} has no atoms");
+        assert_eq!(score(&app), 0.);
+    }
+}
+
+#[test]
+fn level_four_keeps_its_lattice() {
+    let atoms = get_level(4).unwrap().all_atoms();
+
+    // its s_score and any recorded shots were set against exactly these atoms
+    assert_eq!(atoms.len(), 35727);
+    assert!(atoms.iter().all(|atom| atom.position != Vec2::ZERO), "no atom sits on the origin");
+    // on steps of 75 from -8000
+    assert!(atoms.iter().all(|atom| ((atom.position + 8000.) % 75.).abs_diff_eq(Vec2::ZERO, 1e-3)));
+}
+
+#[test]
+fn every_level_reacts_to_a_shot() {
+    for level in levels() {
+        let mut app = load_level(level);
+        let num_neutrons = level_stats(&app).num_neutrons;
+        let atoms = atom_count(&mut app);
+        let shot = shot_at_lowest_atom(&mut app, LAUNCH_SPEED);
+
+        start_run(&mut app, vec![shot]);
+        assert_eq!(level_stats(&app).num_neutrons, num_neutrons - 1, "level {level} didn't use up the placed neutron");
+
+        step(&mut app, 60);
+
+        assert!(score(&app) >= ENERGY_RELEASED, "level {level} scored nothing");
+        assert!(atom_count(&mut app) < atoms, "level {level} lost no atoms");
+    }
+}
+
+#[test]
+fn first_level_clears_with_one_shot() {
+    let mut app = load_level(0);
+    start_run(&mut app, vec![PlacedNeutron { position: Vec2::new(0., -60.), velocity: Vec2::Y * LAUNCH_SPEED }]);
+
+    step(&mut app, 200);
+
+    // the middle atom's two neutrons fan out at 45° straight into the other two atoms, which release three each
+    assert_eq!(atom_count(&mut app), 0);
+    assert_eq!(neutron_velocities(&mut app).len(), 6);
+    assert!((score(&app) - ENERGY_RELEASED * 3.).abs() < ENERGY_RELEASED * 1e-6);
+    assert_eq!(calculate_grade(score(&app), level_stats(&app)), "S");
+    assert_eq!(state(&app), GameState::GAME);
+}
+
+#[test]
+fn split_neutrons_follow_the_pattern() {
+    let mut app = load_level(0);
+    start_run(&mut app, vec![PlacedNeutron { position: Vec2::new(0., -60.), velocity: Vec2::Y * LAUNCH_SPEED }]);
+
+    // just past the first fission, before either split neutron reaches another atom
+    let ticks = (0..60).find(|_| {
+        step(&mut app, 1);
+        atom_count(&mut app) == 2
+    });
+    assert!(ticks.is_some(), "the middle atom never split");
+    step(&mut app, 1);
+
+    let mut angles: Vec<f32> = neutron_velocities(&mut app).iter().map(|velocity| Vec2::Y.angle_between(*velocity)).collect();
+    angles.sort_by(f32::total_cmp);
+
+    assert_eq!(angles.len(), 2);
+    assert!((angles[0] + PI/4.).abs() < 1e-4, "expected -45°, got {}", angles[0].to_degrees());
+    assert!((angles[1] - PI/4.).abs() < 1e-4, "expected 45°, got {}", angles[1].to_degrees());
+
+    for velocity in neutron_velocities(&mut app) {
+        assert!((velocity.length() - LAUNCH_SPEED).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn reaction_ends_when_every_neutron_is_absorbed() {
+    let mut app = load_level_file("(
+        atoms: [(num_neutrons: 3, position: (0., 0.))],
+        absorbers: [(Rectangle(size: (2000., 40.)), (0., 200.))],
+        level_stats: (num_neutrons: 1, num_control_rods: 0, s_score: 3.2e-11),
+    )");
+    start_run(&mut app, vec![PlacedNeutron { position: Vec2::new(0., -60.), velocity: Vec2::Y * LAUNCH_SPEED }]);
+
+    let ticks = run_until_finished(&mut app, 600);
+
+    assert!(ticks.is_some(), "the reaction never finished");
+    assert_eq!(atom_count(&mut app), 0);
+    assert!(neutron_velocities(&mut app).is_empty());
+    assert!((score(&app) - ENERGY_RELEASED).abs() < ENERGY_RELEASED * 1e-6);
+}
+
+#[test]
+fn placements_respect_the_level_limit() {
+    let mut app = load_level(0);
+    let spare = PlacedNeutron { position: Vec2::new(500., -500.), velocity: Vec2::X * LAUNCH_SPEED };
+
+    start_run(&mut app, vec![spare, spare, spare]);
+
+    assert_eq!(level_stats(&app).num_neutrons, 0);
+    assert_eq!(neutron_velocities(&mut app).len(), 1);
+}
+
+#[test]
+fn generated_level_file_loads() {
+    let level = level_file("generated.ron");
+    let mut app = load_level_file(&level);
+    let atoms = atom_count(&mut app);
+
+    assert!(atoms > 50, "only {atoms} atoms were generated");
+
+    // the same seeds give the same level every time
+    assert_eq!(atom_count(&mut load_level_file(&level)), atoms);
+}
+
+#[test]
+fn endless_levels_follow_their_seed() {
+    let level = generate_endless_level(7);
+    let atoms = atom_count(&mut load_level_value(level.clone()));
+
+    assert!(atoms > 0, "no atoms were generated");
+    assert_eq!(atom_count(&mut load_level_value(generate_endless_level(7))), atoms);
+
+    // a straight shot releases something, so the searched target is always reachable
+    assert!(search_s_score(&level, 7) >= ENERGY_RELEASED);
+}
+
+#[test]
+fn objectives_are_settled_by_the_results() {
+    let level = level_file("objectives.ron");
+    let mut app = load_level_file(&level);
+
+    assert_eq!(app.world.resource::<Objectives>().total, AtomCounts { targets: 2, protected: 2, untagged: 2 });
+
+    let shot = shot_at_lowest_atom(&mut app, LAUNCH_SPEED);
+    start_run(&mut app, vec![shot]);
+
+    assert!(run_until_finished(&mut app, 1200).is_some(), "the reaction never finished");
+    assert!(app.world.resource::<Objectives>().objectives.iter().all(|(_, status)| *status != ObjectiveStatus::Pending));
+}
+
+#[test]
+fn example_level_file_sets_up_with_its_grades() {
+    let mut app = load_level_file(&level_file("example.ron"));
+
+    assert_eq!(atom_count(&mut app), 4);
+    assert_eq!(level_stats(&app).grade_thresholds().first().map(|(letter, _)| *letter), Some("S+"));
+}
+
+#[test]
+fn power_is_tracked_through_a_run() {
+    let mut app = load_level(1);
+    let shot = shot_at_lowest_atom(&mut app, LAUNCH_SPEED);
+
+    start_run(&mut app, vec![shot]);
+    run_until_finished(&mut app, 600);
+
+    let power_stats = app.world.resource::<PowerStats>();
+    let fission_energy: f64 = power_stats.fissions.iter().map(|(_, energy)| energy).sum();
+
+    assert!(power_stats.peak_power > 0.);
+    assert!((fission_energy - score(&app)).abs() < ENERGY_RELEASED * 1e-6);
+    // sampled every tick, the running peak matches the best window over the whole run
+    assert!((power_stats.peak_power - power_stats.sustained_power(POWER_WINDOW)).abs() < power_stats.peak_power * 1e-6);
+}